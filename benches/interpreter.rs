@@ -0,0 +1,86 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use chip8emu::audio::Tone;
+use chip8emu::emu8::{get_machine, Machine};
+use chip8emu::error::EmuError;
+
+const STEPS: u64 = 10_000;
+
+type Step = fn(&mut Machine) -> Result<(), EmuError>;
+
+/// A loop of arithmetic, skips, register stores and loads and a sprite draw, roughly
+/// the instruction mix of a game's main loop.
+const GAME_LOOP: [u8; 26] = [
+    0x60, 0x00, // 200: LD V0, 0
+    0x61, 0x01, // 202: LD V1, 1
+    0x70, 0x01, // 204: ADD V0, 1
+    0x80, 0x14, // 206: ADD V0, V1
+    0xA3, 0x00, // 208: LD I, 0x300
+    0xF2, 0x55, // 20A: LD [I], V2
+    0xF2, 0x65, // 20C: LD V2, [I]
+    0x82, 0x03, // 20E: XOR V2, V0
+    0x30, 0x00, // 210: SE V0, 0
+    0x42, 0xFF, // 212: SNE V2, 0xFF
+    0xF0, 0x29, // 214: LD F, V0
+    0xD0, 0x15, // 216: DRW V0, V1, 5
+    0x12, 0x04, // 218: JP 0x204
+];
+
+/// Clears the screen and redraws a row of digits, like a game redrawing every frame.
+const DRAW_HEAVY: [u8; 20] = [
+    0x00, 0xE0, // 200: CLS
+    0x60, 0x00, // 202: LD V0, 0
+    0x61, 0x08, // 204: LD V1, 8
+    0xF0, 0x29, // 206: LD F, V0
+    0xD0, 0x15, // 208: DRW V0, V1, 5
+    0x70, 0x05, // 20A: ADD V0, 5
+    0x30, 0x3C, // 20C: SE V0, 60
+    0x12, 0x06, // 20E: JP 0x206
+    0x12, 0x00, // 210: JP 0x200
+    0x00, 0x00,
+];
+
+/// Calls a subroutine converting a counter to decimal digits and back, exercising the
+/// stack and the memory bus.
+const SUBROUTINES: [u8; 16] = [
+    0x22, 0x06, // 200: CALL 0x206
+    0x70, 0x01, // 202: ADD V0, 1
+    0x12, 0x00, // 204: JP 0x200
+    0xA3, 0x00, // 206: LD I, 0x300
+    0xF0, 0x33, // 208: LD B, V0
+    0xF2, 0x65, // 20A: LD V2, [I]
+    0x00, 0xEE, // 20C: RET
+    0x00, 0x00,
+];
+
+fn steps(machine: &mut Machine, step: Step) {
+    for _ in 0..STEPS {
+        step(machine).unwrap();
+    }
+}
+
+/// The cached interpreter against the reference path on the same program.
+fn paths(c: &mut Criterion) {
+    let mut group = c.benchmark_group("interpreter");
+    group.throughput(Throughput::Elements(STEPS));
+    let paths: [(&str, Step); 2] = [("reference", Machine::step), ("cached", Machine::step_cached)];
+    for (name, step) in paths {
+        let mut machine = get_machine(&GAME_LOOP, Tone::default());
+        group.bench_function(name, |b| b.iter(|| steps(&mut machine, step)));
+    }
+    group.finish();
+}
+
+/// Instructions per second of the cached interpreter on different kinds of programs.
+fn roms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("roms");
+    group.throughput(Throughput::Elements(STEPS));
+    for (name, rom) in [("game_loop", &GAME_LOOP[..]), ("draw_heavy", &DRAW_HEAVY), ("subroutines", &SUBROUTINES)] {
+        let mut machine = get_machine(rom, Tone::default());
+        group.bench_function(name, |b| b.iter(|| steps(&mut machine, Machine::step_cached)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, paths, roms);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use chip8emu::display::{get_world, World, CHIP8_HEIGHT, CHIP8_WIDTH};
+use chip8emu::palette::Palette;
+use chip8emu::phosphor::{Persistence, Phosphor, DEFAULT_DECAY};
+use chip8emu::screenshot::render_scaled;
+
+/// A screen with a checkerboard, so every other pixel is lit.
+fn checkerboard() -> World {
+    let mut world = get_world();
+    for (idx, px) in world.px.iter_mut().enumerate() {
+        *px = ((idx + idx / CHIP8_WIDTH as usize) % 2) as u8;
+    }
+    world
+}
+
+/// `World::draw` into the window's frame buffer with each persistence mode. The window
+/// scales the frame on the GPU, so its cost does not depend on `--scale`.
+fn draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("world_draw");
+    let palette = Palette::default();
+    let mut frame = vec![0; (CHIP8_WIDTH * CHIP8_HEIGHT * 4) as usize];
+    for (name, mode) in [("off", Persistence::Off), ("blend", Persistence::Blend(DEFAULT_DECAY)), ("or", Persistence::Or)] {
+        let mut world = checkerboard();
+        let mut phosphor = Phosphor::new(mode);
+        phosphor.update(&world.px);
+        group.bench_function(name, |b| b.iter(|| world.draw(&mut frame, &palette, &phosphor)));
+    }
+    group.finish();
+}
+
+/// The scaled RGBA image screenshots and recordings are made from.
+fn scaled(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render_scaled");
+    let palette = Palette::default();
+    let world = checkerboard();
+    for scale in [1, 4, 10, 20] {
+        group.bench_with_input(BenchmarkId::from_parameter(scale), &scale, |b, &scale| {
+            b.iter(|| render_scaled(&world, scale, &palette))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, draw, scaled);
+criterion_main!(benches);
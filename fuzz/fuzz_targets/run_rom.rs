@@ -0,0 +1,52 @@
+//! Runs arbitrary bytes as a ROM on a headless machine and fails on any panic. The
+//! first byte picks the quirks, the second the I overflow mode, the timing and the
+//! instructions per frame, the next two the keys held down; the rest is the program.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use chip8emu::audio::Tone;
+use chip8emu::emu8::get_machine;
+use chip8emu::quirks::{IOverflow, Quirks};
+use chip8emu::timing::Timing;
+
+/// Frames run per input: enough for loops, calls and self-modifying code.
+const FRAMES: u32 = 300;
+
+fuzz_target!(|data: &[u8]| {
+    let [quirks, settings, keys_low, keys_high, rom @ ..] = data else {
+        return;
+    };
+    let bit = |byte: u8, n: u8| byte & 1 << n != 0;
+
+    let mut machine = get_machine(rom, Tone::default());
+    machine.cpu.quirks = Quirks {
+        shift: bit(*quirks, 0),
+        memory_increment_by_x: bit(*quirks, 1),
+        memory_leave_i_unchanged: bit(*quirks, 2),
+        wrap: bit(*quirks, 3),
+        jump: bit(*quirks, 4),
+        vblank: bit(*quirks, 5),
+        logic: bit(*quirks, 6),
+    };
+    machine.cpu.i_overflow = match settings & 0b11 {
+        0 | 1 => IOverflow::Wrap,
+        2 => IOverflow::Fault,
+        _ => IOverflow::Flag,
+    };
+    machine.timing = if bit(*settings, 2) { Timing::Vip } else { Timing::Ipf };
+    // 1, the default, to 32 instructions per frame.
+    machine.ipf = 1 + (settings >> 3) as u32;
+    let keys = u16::from_le_bytes([*keys_low, *keys_high]);
+    for (key, down) in machine.keyboard.iter_mut().enumerate() {
+        *down = (keys >> key & 1) as u8;
+    }
+
+    for _ in 0..FRAMES {
+        // A fault stops the machine, as in the front ends; only a panic is a bug.
+        if machine.run_frame().is_err() {
+            break;
+        }
+    }
+});
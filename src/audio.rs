@@ -1,15 +1,176 @@
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
 use rodio::{
+    buffer::SamplesBuffer,
     Sink,
     OutputStream
 };
 
-pub struct Audio {
+pub const SAMPLE_RATE: u32 = 48000;
+/// One 60 Hz frame of audio.
+pub const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+const PRIMED_FRAMES: usize = 2;
+const MAX_QUEUED_FRAMES: usize = 6;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+impl Waveform {
+    pub fn parse(name: &str) -> Result<Waveform, String> {
+        match name {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" => Ok(Waveform::Sawtooth),
+            _ => Err(format!("Unknown waveform: {name} (expected square, sine, triangle or sawtooth)")),
+        }
+    }
+
+    /// Value of the wave at `phase` in `0.0..1.0`, between -1.0 and 1.0.
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+/// How the beeper sounds while the sound timer is running.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tone {
+    pub frequency: f32,
+    /// Output level between 0.0 and 1.0.
+    pub volume: f32,
+    pub waveform: Waveform,
+    /// Fade-in and fade-out times in milliseconds. A few milliseconds are enough
+    /// to remove the click of the wave starting or stopping mid-cycle.
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Tone { frequency: 440.0, volume: 0.25, waveform: Waveform::Square, attack_ms: 2.0, release_ms: 5.0 }
+    }
+}
+
+/// Oscillator with a linear attack/release envelope, producing one sample at a time.
+pub struct Synth {
+    tone: Tone,
+    phase: f32,
+    level: f32,
+}
+
+impl Synth {
+    pub fn new(tone: Tone) -> Self {
+        Synth { tone, phase: 0.0, level: 0.0 }
+    }
+
+    /// Next sample at `SAMPLE_RATE`; `gate` is whether the beeper is on.
+    pub fn next_sample(&mut self, gate: bool) -> f32 {
+        let ramp = |ms: f32| if ms > 0.0 { 1000.0 / (ms * SAMPLE_RATE as f32) } else { 1.0 };
+        self.level = if gate {
+            (self.level + ramp(self.tone.attack_ms)).min(1.0)
+        } else {
+            (self.level - ramp(self.tone.release_ms)).max(0.0)
+        };
+
+        if self.level == 0.0 {
+            // Restart silent waves at the start of a cycle so every beep begins the same way.
+            self.phase = 0.0;
+            return 0.0;
+        }
+
+        let value = self.tone.waveform.sample(self.phase) * self.tone.volume * self.level;
+        self.phase = (self.phase + self.tone.frequency / SAMPLE_RATE as f32).fract();
+        value
+    }
+}
+
+/// Somewhere to send the beeper output, one emulated frame at a time.
+pub trait AudioBackend {
+    /// Consume the `SAMPLES_PER_FRAME` samples of one frame.
+    fn push(&mut self, samples: &[f32]) -> Result<(), String>;
+
+    /// Silence the speakers, e.g. during turbo, while files still get every frame.
+    fn set_muted(&mut self, _muted: bool) {}
+
+    /// Flush any buffered output once the run is over.
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Discards all samples, for muted runs and machines without a sound device.
+pub struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn push(&mut self, _samples: &[f32]) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Sends every frame to several backends, e.g. the sound device and a WAV file.
+pub struct TeeAudio {
+    backends: Vec<Box<dyn AudioBackend>>,
+}
+
+impl AudioBackend for TeeAudio {
+    fn push(&mut self, samples: &[f32]) -> Result<(), String> {
+        self.backends.iter_mut().try_for_each(|backend| backend.push(samples))
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.backends.iter_mut().for_each(|backend| backend.set_muted(muted));
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        self.backends.into_iter().try_for_each(|backend| backend.finish())
+    }
+}
+
+/// Build the audio output for a run. With `device` set the default output device is
+/// opened, falling back to `NullAudio` with a warning when there is none (CI
+/// containers, SSH sessions) or when `mute` is set. With `wav` set every frame is also
+/// written to that file.
+pub fn open_audio(device: bool, mute: bool, wav: Option<&Path>) -> Result<Box<dyn AudioBackend>, String> {
+    let output: Box<dyn AudioBackend> = if !device || mute {
+        Box::new(NullAudio)
+    } else {
+        match RodioAudio::new() {
+            Ok(audio) => Box::new(audio),
+            Err(err) => {
+                eprintln!("Warning: no audio output available ({err}), running without sound");
+                Box::new(NullAudio)
+            }
+        }
+    };
+
+    match wav {
+        Some(path) => Ok(Box::new(TeeAudio { backends: vec![output, Box::new(WavAudio::create(path)?)] })),
+        None => Ok(output),
+    }
+}
+
+/// Plays the samples rendered by the emulator on the default output device.
+pub struct RodioAudio {
     sink: Sink,
-    _stream: OutputStream
+    _stream: OutputStream,
+    muted: bool,
 }
 
-impl Audio {
-    pub fn new() -> Result<Audio, String> {
+impl RodioAudio {
+    pub fn new() -> Result<RodioAudio, String> {
         let (stream, stream_handle) = match OutputStream::try_default() {
             Ok(v) => v,
             Err(err) => { return Err(err.to_string()); }
@@ -18,17 +179,119 @@ impl Audio {
             Ok(v) => v,
             Err(err) => { return Err(err.to_string()); }
         };
-        sink.append(rodio::source::SineWave::new(840));
-        sink.pause();
-        let ret = Audio {sink, _stream: stream};
+        // Start with a little silence queued so a late frame does not starve the device.
+        for _ in 0..PRIMED_FRAMES {
+            sink.append(SamplesBuffer::new(1, SAMPLE_RATE, vec![0.0f32; SAMPLES_PER_FRAME]));
+        }
+        let ret = RodioAudio {sink, _stream: stream, muted: false};
         Ok(ret)
     }
+}
+
+impl AudioBackend for RodioAudio {
+    /// Frames are dropped while the queue is full, which keeps latency bounded when
+    /// the emulator runs ahead of real time.
+    fn push(&mut self, samples: &[f32]) -> Result<(), String> {
+        if !self.muted && self.sink.len() < MAX_QUEUED_FRAMES {
+            self.sink.append(SamplesBuffer::new(1, SAMPLE_RATE, samples.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+}
+
+/// Writes the samples rendered by the emulator to a 16-bit mono WAV file.
+pub struct WavAudio {
+    writer: hound::WavWriter<BufWriter<File>>,
+}
+
+impl WavAudio {
+    pub fn create(path: &Path) -> Result<WavAudio, String> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec).map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(WavAudio { writer })
+    }
+}
+
+impl AudioBackend for WavAudio {
+    fn push(&mut self, samples: &[f32]) -> Result<(), String> {
+        for &sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_sample(value).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        self.writer.finalize().map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emu8::get_machine;
+
+    /// A full-volume 1 Hz square wave, which stays at its peak for the samples a test looks at.
+    fn tone(attack_ms: f32, release_ms: f32) -> Tone {
+        Tone { frequency: 1.0, volume: 1.0, waveform: Waveform::Square, attack_ms, release_ms }
+    }
+
+    fn samples(synth: &mut Synth, gate: bool, count: usize) -> Vec<f32> {
+        (0..count).map(|_| synth.next_sample(gate)).collect()
+    }
+
+    #[test]
+    fn envelope_ramps_linearly() {
+        // 1 ms is 48 samples up, 2 ms 96 samples down.
+        let mut synth = Synth::new(tone(1.0, 2.0));
+        let attack = samples(&mut synth, true, 48);
+        assert!((attack[23] - 0.5).abs() < 1e-4);
+        assert!((attack[47] - 1.0).abs() < 1e-4);
+        assert_eq!(samples(&mut synth, true, 10), [1.0; 10]);
+
+        let release = samples(&mut synth, false, 96);
+        assert!((release[47] - 0.5).abs() < 1e-4);
+        assert!(release[95].abs() < 1e-4);
+        assert_eq!(samples(&mut synth, false, 10), [0.0; 10]);
+    }
+
+    #[test]
+    fn zero_times_switch_instantly() {
+        let mut synth = Synth::new(tone(0.0, 0.0));
+        assert_eq!(samples(&mut synth, true, 3), [1.0; 3]);
+        assert_eq!(samples(&mut synth, false, 3), [0.0; 3]);
+    }
 
-    pub fn play(&self) {
-        self.sink.play();
+    #[test]
+    fn waveforms() {
+        assert_eq!(Waveform::Square.sample(0.25), 1.0);
+        assert_eq!(Waveform::Square.sample(0.75), -1.0);
+        assert!((Waveform::Sine.sample(0.25) - 1.0).abs() < 1e-6);
+        assert_eq!(Waveform::Triangle.sample(0.5), 1.0);
+        assert_eq!(Waveform::Triangle.sample(0.0), -1.0);
+        assert_eq!(Waveform::Sawtooth.sample(0.0), -1.0);
+        assert!(Waveform::parse("noise").is_err());
     }
 
-    pub fn pause(&self) {
-        self.sink.pause();
+    #[test]
+    fn beep_lasts_as_many_frames_as_st() {
+        let mut machine = get_machine(&[0x12, 0x00], tone(0.0, 0.0));
+        machine.cpu.st = 3;
+        for frame in 0..5 {
+            machine.run_frame().unwrap();
+            assert_eq!(machine.samples.len(), SAMPLES_PER_FRAME);
+            let on = machine.samples.iter().all(|&sample| sample != 0.0);
+            let off = machine.samples.iter().all(|&sample| sample == 0.0);
+            assert!(if frame < 3 { on } else { off }, "frame {frame}");
+        }
     }
 }
@@ -0,0 +1,98 @@
+use crate::emu8::Machine;
+use crate::error::EmuError;
+use crate::memory::{Bus, Memory};
+
+/// Executes one instruction on the machine, given its opcode.
+pub type Handler = fn(&mut Machine, u16) -> Result<(), EmuError>;
+
+/// An opcode with the handler that executes it.
+#[derive(Clone, Copy)]
+pub struct Decoded {
+    pub opcode: u16,
+    pub handler: Handler,
+}
+
+/// Instructions decoded from memory, by address. Entries are dropped when a write
+/// through a `CachingBus` changes either of their bytes.
+pub struct DecodeCache {
+    entries: Vec<Option<Decoded>>,
+}
+
+impl Default for DecodeCache {
+    fn default() -> DecodeCache {
+        DecodeCache { entries: vec![None; 0x1000] }
+    }
+}
+
+impl DecodeCache {
+    pub fn get(&self, addr: u16) -> Option<Decoded> {
+        self.entries.get(addr as usize).copied().flatten()
+    }
+
+    pub fn insert(&mut self, addr: u16, decoded: Decoded) {
+        if let Some(entry) = self.entries.get_mut(addr as usize) {
+            *entry = Some(decoded);
+        }
+    }
+
+    /// Forget the instructions holding the byte at `addr`: the one starting there and
+    /// the one starting a byte before, which for 0x000 is the one at 0xFFF.
+    pub fn invalidate(&mut self, addr: u16) {
+        for addr in [addr.wrapping_sub(1) & 0xFFF, addr] {
+            if let Some(entry) = self.entries.get_mut(addr as usize) {
+                *entry = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+}
+
+/// Memory as instructions see it on the cached path: writes also invalidate the cache.
+pub struct CachingBus<'a> {
+    pub memory: &'a mut Memory,
+    pub cache: &'a mut DecodeCache,
+}
+
+impl Bus for CachingBus<'_> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.memory.read(addr)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.cache.invalidate(addr);
+        self.memory.write(addr, value);
+    }
+
+    fn fetch(&mut self, addr: u16) -> u16 {
+        self.memory.fetch(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::Tone;
+    use crate::cpu::Reg;
+    use crate::emu8::get_machine;
+
+    /// Run `6000 | [0x000]` at 0xFFF, change its low byte at 0x000 and run it again.
+    fn rerun_at_fff(step: fn(&mut Machine) -> Result<(), EmuError>) -> u8 {
+        let mut machine = get_machine(&[], Tone::default());
+        (machine.memory.bytes[0xFFF], machine.memory.bytes[0x000]) = (0x60, 0x11);
+        machine.cpu.pc = 0xFFF;
+        step(&mut machine).unwrap();
+        CachingBus { memory: &mut machine.memory, cache: &mut machine.cache }.write(0x000, 0x22);
+        machine.cpu.pc = 0xFFF;
+        step(&mut machine).unwrap();
+        machine.cpu.regs[Reg::new(0)]
+    }
+
+    #[test]
+    fn write_at_0x000_invalidates_opcode_at_0xfff() {
+        assert_eq!(rerun_at_fff(Machine::step), 0x22);
+        assert_eq!(rerun_at_fff(Machine::step_cached), 0x22);
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::audio::{Tone, Waveform};
+use crate::display::{MAX_SCALE, SCALE};
+use crate::font::{Font, FontSet, DEFAULT_FONT_ADDR};
+use crate::hooks::Region;
+use crate::keyslog::DEFAULT_LAYOUT;
+use crate::palette::{Palette, PALETTE_NAMES};
+use crate::platform::Platform;
+use crate::phosphor::{Persistence, DEFAULT_DECAY};
+use crate::quirks::{IOverflow, Quirks};
+use crate::timing::Timing;
+use crate::turbo::Turbo;
+
+/// Options collected from the config file and the command line.
+#[derive(Clone)]
+pub struct Config {
+    pub rom_path: String,
+    /// Target platform, used to validate the ROM.
+    pub platform: Platform,
+    /// Interpreter behaviour differences, by default those of the platform.
+    pub quirks: Quirks,
+    /// What I running past the end of memory does.
+    pub i_overflow: IOverflow,
+    /// Instructions executed per 60 Hz frame.
+    pub ipf: u32,
+    /// Whether frames run `ipf` instructions or follow the VIP's timing, by platform.
+    pub timing: Timing,
+    /// Digit sprites and where they are loaded.
+    pub font: Font,
+    /// Host keys for CHIP-8 keys 0x0..=0xF.
+    pub keymap: [char; 16],
+    /// Program database to look the ROM up in.
+    pub rom_db: Option<PathBuf>,
+    /// Skip the program database lookup, e.g. one set in the config file.
+    pub no_rom_db: bool,
+    pub scale: u32,
+    /// Colors shared by the window, screenshots and recordings.
+    pub palette: Palette,
+    /// Anti-flicker mode applied when presenting frames in the window.
+    pub persistence: Persistence,
+    /// Beeper frequency, volume, waveform and envelope.
+    pub tone: Tone,
+    /// Run headless for this many frames, write the screen to the path and exit.
+    pub screenshot_after: Option<(u32, PathBuf)>,
+    /// Run without sound output.
+    pub mute: bool,
+    /// Also write the beeper output to this WAV file, in any front end.
+    pub wav: Option<PathBuf>,
+    /// Record every frame from startup to this GIF, Y4M file or PNG directory.
+    pub record: Option<PathBuf>,
+    /// Render in the terminal instead of opening a window.
+    pub tui: bool,
+    /// Reload the ROM whenever its file changes.
+    pub watch: bool,
+    /// Install the memory hooks again for a reloaded ROM instead of dropping them.
+    pub keep_hooks: bool,
+    /// Start in turbo mode; Tab toggles it in the window and the terminal.
+    pub turbo: Option<Turbo>,
+    /// Memory to report reads and writes of.
+    pub watchpoints: Vec<Region>,
+    /// Memory the program may not write to.
+    pub read_only: Vec<Region>,
+    /// Log every memory access at debug level.
+    pub trace_memory: bool,
+    /// Report writes to memory that has already been executed.
+    pub flag_smc: bool,
+}
+
+/// Parse the command line. Options from the config file (`--config <file>`, or
+/// `~/.config/chip8emu.conf` when present) are applied first so the command line wins.
+pub fn parse_args(args: &[String]) -> Result<Config, String> {
+    parse_layers(args, None)
+}
+
+/// Parse the command line again once the ROM is known. Settings for it are layered as
+/// config file < program database (`rom_args`) < `[sha1]` section of the config file
+/// < command line.
+pub fn parse_args_for_rom(args: &[String], sha1: &str, rom_args: &[String]) -> Result<Config, String> {
+    parse_layers(args, Some((sha1, rom_args)))
+}
+
+fn parse_layers(args: &[String], rom: Option<(&str, &[String])>) -> Result<Config, String> {
+    let cli_args = &args[1.min(args.len())..];
+    let config_path = match cli_args.iter().position(|arg| arg == "--config") {
+        Some(idx) => Some(PathBuf::from(cli_args.get(idx + 1).ok_or("Missing value for --config")?)),
+        None => default_config_path().filter(|path| path.is_file()),
+    };
+
+    let mut file = match config_path {
+        Some(path) => read_config_file(&path)?,
+        None => ConfigFile::default(),
+    };
+    let mut all_args = file.args;
+    if let Some((sha1, rom_args)) = rom {
+        all_args.extend(rom_args.iter().cloned());
+        all_args.extend(file.roms.remove(sha1).unwrap_or_default());
+    }
+    all_args.extend(cli_args.iter().cloned());
+    parse_options(&all_args)
+}
+
+/// Parse options as given, without the program name and without reading a config file.
+pub fn parse_options(all_args: &[String]) -> Result<Config, String> {
+    let mut rom_path = None;
+    let mut platform = Platform::Chip8;
+    let mut quirks = None;
+    let mut i_overflow = IOverflow::default();
+    let mut ipf = 1;
+    let mut font_set = None;
+    let mut font_addr = DEFAULT_FONT_ADDR;
+    let mut keymap = DEFAULT_LAYOUT;
+    let mut rom_db = None;
+    let mut no_rom_db = false;
+    let mut scale = SCALE;
+    let mut palette = Palette::default();
+    let mut persistence = "off".to_string();
+    let mut decay = DEFAULT_DECAY;
+    let mut tone = Tone::default();
+    let mut screenshot_after = None;
+    let mut record = None;
+    let mut mute = false;
+    let mut wav = None;
+    let mut tui = false;
+    let mut watch = false;
+    let mut keep_hooks = false;
+    let mut turbo = None;
+    let mut watchpoints = Vec::new();
+    let mut read_only = Vec::new();
+    let mut trace_memory = false;
+    let mut flag_smc = false;
+
+    let mut iter = all_args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => {
+                next_value(&mut iter, arg)?;
+            }
+            "--platform" => platform = Platform::parse(next_value(&mut iter, arg)?)?,
+            "--quirks" => quirks = Some(Quirks::parse(next_value(&mut iter, arg)?)?),
+            "--i-overflow" => i_overflow = IOverflow::parse(next_value(&mut iter, arg)?)?,
+            "--ipf" => {
+                ipf = parse_num(next_value(&mut iter, arg)?, arg)?;
+                if ipf == 0 {
+                    return Err("--ipf must be at least 1".to_string());
+                }
+            }
+            "--font" => font_set = Some(FontSet::parse(next_value(&mut iter, arg)?)?),
+            "--font-addr" => {
+                let value = next_value(&mut iter, arg)?;
+                font_addr = u16::from_str_radix(value.trim_start_matches("0x"), 16)
+                    .map_err(|_| format!("Invalid address for {arg}: {value} (expected hex, e.g. 0x50)"))?;
+            }
+            "--keymap" => keymap = parse_keymap(next_value(&mut iter, arg)?)?,
+            "--rom-db" => rom_db = Some(PathBuf::from(next_value(&mut iter, arg)?)),
+            "--no-rom-db" => no_rom_db = true,
+            "--scale" => {
+                scale = parse_num(next_value(&mut iter, arg)?, arg)?;
+                if !(1..=MAX_SCALE).contains(&scale) {
+                    return Err(format!("--scale must be between 1 and {MAX_SCALE}"));
+                }
+            }
+            "--palette" => {
+                let name = next_value(&mut iter, arg)?;
+                palette = Palette::named(name).ok_or(format!(
+                    "Unknown palette: {name} (expected one of {})",
+                    PALETTE_NAMES.join(", ")
+                ))?;
+            }
+            "--colors" => palette = Palette::parse(next_value(&mut iter, arg)?)?,
+            "--persistence" => persistence = next_value(&mut iter, arg)?.clone(),
+            "--decay" => {
+                decay = parse_float(next_value(&mut iter, arg)?, arg)?;
+                if !(0.0..=1.0).contains(&decay) {
+                    return Err("--decay must be between 0.0 and 1.0".to_string());
+                }
+            }
+            "--tone" => {
+                tone.frequency = parse_float(next_value(&mut iter, arg)?, arg)?;
+                if !(tone.frequency.is_finite() && tone.frequency > 0.0) {
+                    return Err("--tone must be a positive frequency in Hz".to_string());
+                }
+            }
+            "--volume" => {
+                tone.volume = parse_float(next_value(&mut iter, arg)?, arg)?;
+                if !(0.0..=1.0).contains(&tone.volume) {
+                    return Err("--volume must be between 0.0 and 1.0".to_string());
+                }
+            }
+            "--waveform" => tone.waveform = Waveform::parse(next_value(&mut iter, arg)?)?,
+            "--attack" => tone.attack_ms = parse_float(next_value(&mut iter, arg)?, arg)?.max(0.0),
+            "--release" => tone.release_ms = parse_float(next_value(&mut iter, arg)?, arg)?.max(0.0),
+            "--screenshot-after" => {
+                let frames = parse_num(next_value(&mut iter, arg)?, arg)?;
+                let path = PathBuf::from(next_value(&mut iter, arg)?);
+                screenshot_after = Some((frames, path));
+            }
+            "--record" => record = Some(PathBuf::from(next_value(&mut iter, arg)?)),
+            "--mute" => mute = true,
+            "--wav" => wav = Some(PathBuf::from(next_value(&mut iter, arg)?)),
+            "--tui" => tui = true,
+            "--watch" => watch = true,
+            "--keep-hooks" => keep_hooks = true,
+            "--turbo" => turbo = Some(Turbo::parse(next_value(&mut iter, arg)?)?),
+            "--watchpoint" => watchpoints.extend(parse_regions(next_value(&mut iter, arg)?)?),
+            "--read-only" => read_only.extend(parse_regions(next_value(&mut iter, arg)?)?),
+            "--trace-memory" => trace_memory = true,
+            "--flag-smc" => flag_smc = true,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option: {arg}")),
+            _ if rom_path.is_none() && !arg.is_empty() => rom_path = Some(arg.clone()),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    let rom_path = rom_path.ok_or("No filepath argument provided or argument is empty.")?;
+    let persistence = Persistence::parse(&persistence, decay)?;
+    let quirks = quirks.unwrap_or(Quirks::for_platform(platform));
+    let timing = Timing::for_platform(platform);
+    let font = Font::new(font_set.unwrap_or(FontSet::for_platform(platform)), font_addr)?;
+    Ok(Config {
+        rom_path,
+        platform,
+        quirks,
+        i_overflow,
+        ipf,
+        timing,
+        font,
+        keymap,
+        rom_db,
+        no_rom_db,
+        scale, 
+        palette,
+        persistence,
+        tone,
+        screenshot_after,
+        mute,
+        wav,
+        record,
+        tui,
+        watch,
+        keep_hooks,
+        turbo,
+        watchpoints,
+        read_only,
+        trace_memory,
+        flag_smc,
+    })
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".config").join("chip8emu.conf"))
+}
+
+/// Options from a config file, as command line arguments.
+#[derive(Default)]
+struct ConfigFile {
+    /// Options for every ROM.
+    args: Vec<String>,
+    /// Options from `[sha1]` sections, applied only to the ROM with that SHA-1.
+    roms: HashMap<String, Vec<String>>,
+}
+
+/// Turn `key = value` lines into the equivalent `--key value` arguments. Lines after a
+/// `[sha1]` header only apply to that ROM. Blank lines and lines starting with `#`
+/// are ignored.
+fn read_config_file(path: &PathBuf) -> Result<ConfigFile, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut file = ConfigFile::default();
+    let mut section = None;
+
+    for (num, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(sha1) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            section = Some(sha1.trim().to_ascii_lowercase());
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or(format!("{}:{}: expected `key = value`", path.display(), num + 1))?;
+        let args = match &section {
+            Some(sha1) => file.roms.entry(sha1.clone()).or_default(),
+            None => &mut file.args,
+        };
+        // Flags take `true` or `false` instead of a value.
+        match value.trim() {
+            "true" => args.push(format!("--{}", key.trim())),
+            "false" => {}
+            value => {
+                args.push(format!("--{}", key.trim()));
+                args.extend(value.split_whitespace().map(str::to_string));
+            }
+        }
+    }
+    Ok(file)
+}
+
+/// Parse the 16 host keys for CHIP-8 keys 0x0..=0xF, e.g. `x123qweasdzc4rfv`.
+fn parse_keymap(keys: &str) -> Result<[char; 16], String> {
+    let keys: Vec<char> = keys.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let valid = keys.iter().all(char::is_ascii_alphanumeric)
+        && keys.iter().enumerate().all(|(i, c)| !keys[..i].contains(c));
+    match keys.try_into() {
+        Ok(keymap) if valid => Ok(keymap),
+        _ => Err("--keymap expects 16 different letters or digits, for CHIP-8 keys 0 to F".to_string()),
+    }
+}
+
+fn parse_regions(list: &str) -> Result<Vec<Region>, String> {
+    list.split(',').map(Region::parse).collect()
+}
+
+fn next_value<'a>(iter: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<&'a String, String> {
+    iter.next().ok_or(format!("Missing value for {flag}"))
+}
+
+fn parse_num(value: &str, flag: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("Invalid number for {flag}: {value}"))
+}
+
+fn parse_float(value: &str, flag: &str) -> Result<f32, String> {
+    value.parse().ok().filter(|v: &f32| v.is_finite()).ok_or(format!("Invalid number for {flag}: {value}"))
+}
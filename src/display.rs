@@ -1,6 +1,7 @@
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
 
+use std::path::Path;
 use std::time::{Instant, Duration};
 use error_iter::ErrorIter as _;
 use log::error;
@@ -12,15 +13,23 @@ use winit::keyboard::KeyCode;
 use winit::window::WindowBuilder;
 use winit_input_helper::WinitInputHelper;
 
-use crate::audio::Audio;
-use crate::cpu::CPU;
-use crate::instructions::{add_byte_to_reg, add_op, add_to_i, and_op, call_addr, clear_screen, draw_sprite, dt_ld, jump_inst, jump_to_loc, ld_b, ld_dt, ld_f, ld_i, ld_mem_to_i, ld_reg_to_reg, ld_to_reg, ld_v, ld_vx_k, or_op, rand_op, ret, shl_op, shr_op, skip_next_eq, skip_next_eq_regs, skip_next_not_eq, sknp_op, skp_op, sne_op, st_ld, sub_op, subn_op, xor_op};
-use crate::keyslog::get_keyboard_map;
-use crate::memory::read_memory;
+use crate::audio::AudioBackend;
+use crate::config::Config;
+use crate::emu8::{reload, Machine};
+use crate::error::EmuError;
+use crate::menu::{Menu, MenuKey, MENU_HEIGHT, MENU_WIDTH};
+use crate::palette::Palette;
+use crate::phosphor::Phosphor;
+use crate::recorder::Recorder;
+use crate::screenshot::save_png;
+use crate::turbo::Turbo;
+use crate::watch::RomWatcher;
 
 pub const CHIP8_WIDTH: u32 = 64;
 pub const CHIP8_HEIGHT: u32 = 32;
 pub const SCALE: u32 = 10;
+/// Largest `--scale`, keeping the scaled width within the u16 sizes of GIF frames.
+pub const MAX_SCALE: u32 = u16::MAX as u32 / CHIP8_WIDTH;
 
 /// Representation of the application state. In this example, a box will bounce around the screen.
 pub struct World {
@@ -32,18 +41,54 @@ pub fn get_world() -> World {
     World::new()
 }
 
-pub fn init_display(world: &mut World, mem_arr: &mut [u8; 4096], cpu: &mut CPU, audio: &mut Audio) -> Result<(), Error> {
-    env_logger::init();
+/// A program running in the window.
+pub struct Session {
+    pub machine: Machine,
+    pub config: Config,
+    /// Shown in the window title.
+    pub name: String,
+    /// Reloads the ROM when its file changes, with `--watch`.
+    pub watcher: Option<RomWatcher>,
+}
+
+/// Loads the ROM at an index of the picker into a new session.
+pub type Loader<'a> = dyn FnMut(usize) -> Result<Session, String> + 'a;
+
+/// Run a single program in the window until it is closed.
+pub fn init_display(session: Session, audio: &mut dyn AudioBackend, recorder: Option<Recorder>) -> Result<(), Error> {
+    let config = session.config.clone();
+    run_window(&config, Some(session), None, audio, recorder)
+}
 
+/// Show a menu of ROMs in the window. The chosen ROM is loaded with `load`; `Esc`
+/// returns from a running program to the menu.
+pub fn init_display_picker(
+    menu: Menu,
+    config: &Config,
+    load: &mut Loader,
+    audio: &mut dyn AudioBackend,
+    recorder: Option<Recorder>,
+) -> Result<(), Error> {
+    run_window(config, None, Some((menu, load)), audio, recorder)
+}
+
+fn run_window(
+    config: &Config,
+    mut session: Option<Session>,
+    mut picker: Option<(Menu, &mut Loader)>,
+    audio: &mut dyn AudioBackend,
+    mut recorder: Option<Recorder>,
+) -> Result<(), Error> {
     let event_loop = EventLoop::new().unwrap();
     let mut input = WinitInputHelper::new();
+    let mut playback = Playback::new(session.as_ref().map_or(config.turbo, |session| session.config.turbo));
     let window = {
         let size = LogicalSize::new(
-            (CHIP8_WIDTH * SCALE) as f64,
-            (CHIP8_HEIGHT * SCALE) as f64,
+            (CHIP8_WIDTH * config.scale) as f64,
+            (CHIP8_HEIGHT * config.scale) as f64,
         );
         WindowBuilder::new()
-            .with_title("CHIP8 EMU")
+            .with_title(window_title(session.as_ref().map(|session| session.name.as_str()), &playback))
             .with_inner_size(size)
             .with_min_inner_size(size)
             .build(&event_loop)
@@ -51,12 +96,16 @@ pub fn init_display(world: &mut World, mem_arr: &mut [u8; 4096], cpu: &mut CPU,
     };
 
     let mut pixels = {
-        let window_size = window.inner_size();
-        let surface_texture = SurfaceTexture::new(CHIP8_WIDTH * SCALE, CHIP8_HEIGHT * SCALE, &window);
-        Pixels::new(CHIP8_WIDTH, CHIP8_HEIGHT, surface_texture)?
+        let surface_texture = SurfaceTexture::new(CHIP8_WIDTH * config.scale, CHIP8_HEIGHT * config.scale, &window);
+        match session {
+            Some(_) => Pixels::new(CHIP8_WIDTH, CHIP8_HEIGHT, surface_texture)?,
+            None => Pixels::new(MENU_WIDTH, MENU_HEIGHT, surface_texture)?,
+        }
     };
 
-    let mut keyboard = get_keyboard_map();
+    let mut screenshots = 0;
+    let mut recordings = 0;
+    let mut phosphor = Phosphor::new(config.persistence);
 
     let mut last_frame = Instant::now();
     let frame_duration = Duration::from_secs_f64(1.0 / 60.0);
@@ -64,219 +113,163 @@ pub fn init_display(world: &mut World, mem_arr: &mut [u8; 4096], cpu: &mut CPU,
     let res = event_loop.run(|event, elwt| {
         if last_frame.elapsed() > frame_duration {
             last_frame = Instant::now();
-            let hi: u16 = (read_memory(&mem_arr, cpu.pc) as u16) << 8;
-            let lo: u16 = read_memory(&mem_arr, cpu.pc + 1) as u16;
-            let opcode: u16 = hi | lo;
-
-            println!("Opcode val: {:#02x}", opcode);
 
-            if cpu.dt > 0 {
-                cpu.dt -= 1;
-            }
-
-            if cpu.st > 0 {
-                audio.play();
-                cpu.st -= 1;
-            } else {
-                audio.pause();
-            }
+            if let Some(Session { machine, config, name, watcher }) = session.as_mut() {
+                if let Some((reloaded, rom)) = watcher.as_mut().and_then(|watcher| watcher.poll(config)) {
+                    println!("Reloaded {name}");
+                    reload(machine, &rom, &reloaded);
+                    *config = reloaded;
+                }
 
-            // Corrected opcode decoding for CHIP-8
-            match opcode & 0xF000 {
-                0x0000 => match opcode {
-                    0x00E0 => clear_screen(world, cpu, opcode), // CLS
-                    0x00EE => ret(cpu, opcode),            // RET
-                    _ => println!("Unknown 0x0NNN opcode"),
-                },
-                0x1000 => jump_inst(cpu, opcode),           // JP addr
-                0x2000 => call_addr(cpu, opcode),           // CALL addr
-                0x3000 => skip_next_eq(cpu, opcode),        // SE Vx, byte
-                0x4000 => skip_next_not_eq(cpu, opcode),    // SNE Vx, byte
-                0x5000 => skip_next_eq_regs(cpu, opcode),   // SE Vx, Vy
-                0x6000 => ld_to_reg(cpu, opcode),           // LD Vx, byte
-                0x7000 => add_byte_to_reg(cpu, opcode),     // ADD Vx, byte
-                0x8000 => match opcode & 0x000F {
-                    0x0 => ld_reg_to_reg(cpu, opcode),      // LD Vx, Vy
-                    0x1 => or_op(cpu, opcode),              // OR Vx, Vy
-                    0x2 => and_op(cpu, opcode),             // AND Vx, Vy
-                    0x3 => xor_op(cpu, opcode),             // XOR Vx, Vy
-                    0x4 => add_op(cpu, opcode),             // ADD Vx, Vy
-                    0x5 => sub_op(cpu, opcode),             // SUB Vx, Vy
-                    0x6 => shr_op(cpu, opcode),             // SHR Vx {, Vy}
-                    0x7 => subn_op(cpu, opcode),            // SUBN Vx, Vy
-                    0xE => shl_op(cpu, opcode),             // SHL Vx {, Vy}
-                    _ => println!("Unknown 0x8XY? opcode"),
-                },
-                0x9000 => sne_op(cpu, opcode),              // SNE Vx, Vy
-                0xA000 => ld_mem_to_i(cpu, opcode),         // LD I, addr
-                0xB000 => jump_to_loc(cpu, opcode),         // JP V0, addr
-                0xC000 => rand_op(cpu, opcode),             // RND Vx, byte
-                0xD000 => draw_sprite(cpu, mem_arr, world, opcode), // DRW Vx, Vy, nibble
-                0xE000 => match opcode & 0x00FF {
-                    0x9E => skp_op(cpu, opcode, &mut keyboard),  // SKP Vx
-                    0xA1 => sknp_op(cpu, opcode, &mut keyboard), // SKNP Vx
-                    _ => println!("Unknown 0xEX?? opcode"),
-                },
-                0xF000 => match opcode & 0x00FF {
-                    0x07 => ld_dt(cpu, opcode),                  // LD Vx, DT
-                    0x0A => ld_vx_k(cpu, opcode, &mut keyboard), // LD Vx, K
-                    0x15 => dt_ld(cpu, opcode),                  // LD DT, Vx
-                    0x18 => st_ld(cpu, opcode),                  // LD ST, Vx
-                    0x1E => add_to_i(cpu, opcode),               // ADD I, Vx
-                    0x29 => ld_f(cpu, opcode),                   // LD F, Vx
-                    0x33 => ld_b(mem_arr, cpu, opcode),          // LD B, Vx
-                    0x55 => ld_i(mem_arr, cpu, opcode),          // LD [I], Vx
-                    0x65 => ld_v(mem_arr, cpu, opcode),          // LD Vx, [I]
-                    _ => println!("Unknown 0xFX?? opcode"),
-                },
-                _ => println!("Unknown opcode: {:#04x}", opcode),
+                let result = match playback.turbo {
+                    Some(turbo) if !playback.paused => {
+                        // The speakers stay quiet while a WAV export still gets every frame.
+                        audio.set_muted(true);
+                        let result = turbo.run_tick(machine, |machine| {
+                            push_audio(machine, audio);
+                            record_frame(machine, &mut phosphor, &mut recorder);
+                        });
+                        audio.set_muted(false);
+                        result.map(|_| ())
+                    }
+                    _ => (0..playback.frames_due())
+                        .try_for_each(|_| emulate_frame(machine, audio, &mut phosphor, &mut recorder)),
+                };
+                // A fault pauses the machine at the faulting instruction; F2 resets it.
+                if let Err(err) = result {
+                    eprintln!("Error: {err}");
+                    playback.paused = true;
+                    window.set_title(&window_title(Some(name), &playback));
+                }
             }
         }
 
-
-
         // Handle input events
         if input.update(&event) {
-            // Close events
-            if input.key_pressed(KeyCode::Escape) || input.close_requested() {
+            // Close events; Esc goes back to the menu when there is one
+            if input.close_requested() || (input.key_pressed(KeyCode::Escape) && (session.is_none() || picker.is_none())) {
+                if let Some(rec) = recorder.take() {
+                    stop_recording(rec);
+                }
                 elwt.exit();
                 return;
             }
 
-            if input.key_pressed(KeyCode::Digit1) {
-                keyboard.insert(1, 1); 
-            }
-
-            if input.key_released(KeyCode::Digit1) {
-                keyboard.insert(1, 0);
-            }
-
-            if input.key_pressed(KeyCode::Digit2) {
-                keyboard.insert(2, 1) ;
-            }
-
-            if input.key_released(KeyCode::Digit2) {
-                keyboard.insert(2, 0);
-            }
-
-            if input.key_pressed(KeyCode::Digit3) {
-                keyboard.insert(3, 1);
-            }
-
-            if input.key_released(KeyCode::Digit3) {
-                keyboard.insert(3, 0);
-            }
-
-            if input.key_pressed(KeyCode::Digit4) {
-                keyboard.insert(0xC, 1); 
-            }
-
-            if input.key_released(KeyCode::Digit4) {
-                keyboard.insert(0xC, 0);
-            }
-
-            if input.key_pressed(KeyCode::KeyQ) {
-                keyboard.insert(4, 1);
-            }
-
-            if input.key_released(KeyCode::KeyQ) {
-                keyboard.insert(4, 0);
-            }
-
-            if input.key_pressed(KeyCode::KeyW) {
-                keyboard.insert(5, 1);
-            }
-
-            if input.key_released(KeyCode::KeyW) {
-                keyboard.insert(5, 0);
-            }
-
-            if input.key_pressed(KeyCode::KeyE) {
-                keyboard.insert(6, 1);
-            }
-
-            if input.key_released(KeyCode::KeyE) {
-                keyboard.insert(6, 0);
-            }
-
-            if input.key_pressed(KeyCode::KeyR) {
-                keyboard.insert(0xD, 1);
-            }
-
-            if input.key_released(KeyCode::KeyR) {
-                keyboard.insert(0xD, 0);
-            }
-
-            if input.key_pressed(KeyCode::KeyA) {
-                keyboard.insert(7, 1);
-            }
-
-            if input.key_released(KeyCode::KeyA) {
-                keyboard.insert(7, 0);
-            }
-
-            if input.key_pressed(KeyCode::KeyS) {
-                keyboard.insert(8, 1);
-            }
-
-            if input.key_released(KeyCode::KeyS) {
-                keyboard.insert(8, 0);
-            }
-
-            if input.key_pressed(KeyCode::KeyD) {
-                keyboard.insert(9, 1);
-            }
-
-            if input.key_released(KeyCode::KeyD) {
-                keyboard.insert(9, 0);
-            }
-
-            if input.key_pressed(KeyCode::KeyF) {
-                keyboard.insert(0xE, 1);
-            }
-
-            if input.key_released(KeyCode::KeyF) {
-                keyboard.insert(0xE, 0);
+            // Resize the window
+            if let Some(size) = input.window_resized() {
+                if let Err(err) = pixels.resize_surface(size.width, size.height) {
+                    log_error("pixels.resize_surface", err);
+                    elwt.exit();
+                    return;
+                }
             }
 
-            if input.key_pressed(KeyCode::KeyZ) {
-                keyboard.insert(0xA, 1);
+            if input.key_pressed(KeyCode::Escape) {
+                session = None;
+                window.set_title(&window_title(None, &playback));
+                if let Err(err) = pixels.resize_buffer(MENU_WIDTH, MENU_HEIGHT) {
+                    log_error("pixels.resize_buffer", err);
+                    elwt.exit();
+                }
+                return;
             }
 
-            if input.key_released(KeyCode::KeyZ) {
-                keyboard.insert(0xA, 0);
-            }
+            let Some(Session { machine, config, name, .. }) = session.as_mut() else {
+                let Some((menu, load)) = picker.as_mut() else { return };
+                let keys = [
+                    (KeyCode::ArrowUp, MenuKey::Up),
+                    (KeyCode::ArrowDown, MenuKey::Down),
+                    (KeyCode::PageUp, MenuKey::PageUp),
+                    (KeyCode::PageDown, MenuKey::PageDown),
+                    (KeyCode::Home, MenuKey::Home),
+                    (KeyCode::End, MenuKey::End),
+                ];
+                for (code, key) in keys {
+                    if input.key_pressed_os(code) {
+                        menu.key(key);
+                    }
+                }
 
-            if input.key_pressed(KeyCode::KeyX) {
-                keyboard.insert(0, 1);
-            }
+                if input.key_pressed(KeyCode::Enter) {
+                    match load(menu.selected) {
+                        Ok(loaded) => {
+                            phosphor = Phosphor::new(loaded.config.persistence);
+                            playback = Playback::new(loaded.config.turbo);
+                            window.set_title(&window_title(Some(&loaded.name), &playback));
+                            session = Some(loaded);
+                            if let Err(err) = pixels.resize_buffer(CHIP8_WIDTH, CHIP8_HEIGHT) {
+                                log_error("pixels.resize_buffer", err);
+                                elwt.exit();
+                            }
+                        }
+                        Err(err) => menu.status = err,
+                    }
+                }
+                return;
+            };
 
-            if input.key_released(KeyCode::KeyX) {
-                keyboard.insert(0, 0);
+            let old_playback = playback;
+            if input.key_pressed(KeyCode::F2) {
+                machine.reset();
+                phosphor = Phosphor::new(config.persistence);
+                println!("Reset {name}");
             }
-
-            if input.key_pressed(KeyCode::KeyC) {
-                keyboard.insert(0xB, 1);
+            if input.key_pressed(KeyCode::F3) {
+                playback.paused = !playback.paused;
             }
-
-            if input.key_released(KeyCode::KeyC) {
-                keyboard.insert(0xB, 0);
+            if input.key_pressed_os(KeyCode::F4) && playback.paused {
+                if let Err(err) = emulate_frame(machine, audio, &mut phosphor, &mut recorder) {
+                    eprintln!("Error: {err}");
+                }
             }
-
-            if input.key_pressed(KeyCode::KeyV) {
-                keyboard.insert(0xF, 1);
+            if input.key_pressed(KeyCode::F7) {
+                playback.speed = playback.speed.saturating_sub(1);
+            }
+            if input.key_pressed(KeyCode::F8) {
+                playback.speed = (playback.speed + 1).min(SPEEDS.len() - 1);
+            }
+            if input.key_pressed(KeyCode::Tab) {
+                playback.turbo = match playback.turbo {
+                    Some(_) => None,
+                    None => Some(playback.turbo_speed),
+                };
+            }
+            if playback != old_playback {
+                window.set_title(&window_title(Some(name), &playback));
+            }
+
+            if input.key_pressed(KeyCode::F10) {
+                if let Some(rec) = recorder.take() {
+                    stop_recording(rec);
+                } else {
+                    recordings += 1;
+                    let path = format!("recording-{recordings}.gif");
+                    match Recorder::start(Path::new(&path), config.scale, &config.palette) {
+                        Ok(rec) => {
+                            println!("Recording to {path}");
+                            recorder = Some(rec);
+                        }
+                        Err(err) => error!("Recording failed: {err}"),
+                    }
+                }
             }
 
-            if input.key_released(KeyCode::KeyV) {
-                keyboard.insert(0xF, 0);
+            if input.key_pressed(KeyCode::F12) {
+                screenshots += 1;
+                let path = format!("screenshot-{screenshots}.png");
+                match save_png(&machine.world, Path::new(&path), config.scale, &config.palette) {
+                    Ok(()) => println!("Screenshot saved to {path}"),
+                    Err(err) => error!("Screenshot failed: {err}"),
+                }
             }
 
-            // Resize the window
-            if let Some(size) = input.window_resized() {
-                if let Err(err) = pixels.resize_surface(size.width, size.height) {
-                    log_error("pixels.resize_surface", err);
-                    elwt.exit();
-                    return;
+            for (key, &c) in config.keymap.iter().enumerate() {
+                let Some(code) = key_code(c) else { continue };
+                if input.key_pressed(code) {
+                    machine.keyboard[key] = 1;
+                }
+                if input.key_released(code) {
+                    machine.keyboard[key] = 0;
                 }
             }
         }
@@ -287,7 +280,11 @@ pub fn init_display(world: &mut World, mem_arr: &mut [u8; 4096], cpu: &mut CPU,
             ..
         } = event
         {
-            world.draw(pixels.frame_mut());
+            match (session.as_mut(), picker.as_ref()) {
+                (Some(Session { machine, config, .. }), _) => machine.world.draw(pixels.frame_mut(), &config.palette, &phosphor),
+                (None, Some((menu, _))) => menu.draw(pixels.frame_mut(), &config.palette),
+                (None, None) => {}
+            }
             if let Err(err) = pixels.render() {
                 log_error("pixels.render", err);
                 elwt.exit();
@@ -301,6 +298,113 @@ pub fn init_display(world: &mut World, mem_arr: &mut [u8; 4096], cpu: &mut CPU,
     res.map_err(|e| Error::UserDefined(Box::new(e)))
 }
 
+/// Playback speeds selectable with F7 and F8, as multiples of 60 frames per second.
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+
+/// Pause and speed state of the window, shown in its title.
+#[derive(Clone, Copy, PartialEq)]
+struct Playback {
+    paused: bool,
+    /// Index into `SPEEDS`.
+    speed: usize,
+    /// Fraction of a frame carried over between ticks at slow speeds.
+    carry: f32,
+    /// Turbo mode while it is on, overriding `speed`.
+    turbo: Option<Turbo>,
+    /// The turbo speed Tab switches to.
+    turbo_speed: Turbo,
+}
+
+impl Playback {
+    fn new(turbo: Option<Turbo>) -> Playback {
+        Playback { paused: false, speed: NORMAL_SPEED, carry: 0.0, turbo, turbo_speed: turbo.unwrap_or(Turbo::Max) }
+    }
+
+    /// Number of frames to emulate in this 60 Hz tick.
+    fn frames_due(&mut self) -> u32 {
+        if self.paused {
+            return 0;
+        }
+        self.carry += SPEEDS[self.speed];
+        let frames = self.carry.floor();
+        self.carry -= frames;
+        frames as u32
+    }
+}
+
+/// Run one frame and send it to the audio output, the phosphor history and the recorder.
+fn emulate_frame(
+    machine: &mut Machine,
+    audio: &mut dyn AudioBackend,
+    phosphor: &mut Phosphor,
+    recorder: &mut Option<Recorder>,
+) -> Result<(), EmuError> {
+    machine.run_frame()?;
+    push_audio(machine, audio);
+    record_frame(machine, phosphor, recorder);
+    Ok(())
+}
+
+/// Send a finished frame's samples to the audio output.
+fn push_audio(machine: &Machine, audio: &mut dyn AudioBackend) {
+    if let Err(err) = audio.push(&machine.samples) {
+        error!("Audio output failed: {err}");
+    }
+}
+
+/// Add a finished frame to the phosphor history and the recording.
+fn record_frame(machine: &Machine, phosphor: &mut Phosphor, recorder: &mut Option<Recorder>) {
+    phosphor.update(&machine.world.px);
+
+    if let Some(rec) = recorder.as_mut() {
+        if let Err(err) = rec.capture(&machine.world) {
+            error!("Recording failed: {err}");
+            *recorder = None;
+        }
+    }
+}
+
+fn window_title(name: Option<&str>, playback: &Playback) -> String {
+    let Some(name) = name else { return "CHIP8 EMU".to_string() };
+    let mut title = format!("CHIP8 EMU - {name}");
+    if playback.paused {
+        title += " - paused";
+    }
+    if let Some(turbo) = playback.turbo {
+        title += &format!(" - turbo {turbo}");
+    } else if playback.speed != NORMAL_SPEED {
+        title += &format!(" - {}x", SPEEDS[playback.speed]);
+    }
+    title
+}
+
+/// The winit key for a layout character, for letters and digits.
+fn key_code(c: char) -> Option<KeyCode> {
+    let code = match c.to_ascii_lowercase() {
+        '0' => KeyCode::Digit0, '1' => KeyCode::Digit1, '2' => KeyCode::Digit2, '3' => KeyCode::Digit3,
+        '4' => KeyCode::Digit4, '5' => KeyCode::Digit5, '6' => KeyCode::Digit6, '7' => KeyCode::Digit7,
+        '8' => KeyCode::Digit8, '9' => KeyCode::Digit9,
+        'a' => KeyCode::KeyA, 'b' => KeyCode::KeyB, 'c' => KeyCode::KeyC, 'd' => KeyCode::KeyD,
+        'e' => KeyCode::KeyE, 'f' => KeyCode::KeyF, 'g' => KeyCode::KeyG, 'h' => KeyCode::KeyH,
+        'i' => KeyCode::KeyI, 'j' => KeyCode::KeyJ, 'k' => KeyCode::KeyK, 'l' => KeyCode::KeyL,
+        'm' => KeyCode::KeyM, 'n' => KeyCode::KeyN, 'o' => KeyCode::KeyO, 'p' => KeyCode::KeyP,
+        'q' => KeyCode::KeyQ, 'r' => KeyCode::KeyR, 's' => KeyCode::KeyS, 't' => KeyCode::KeyT,
+        'u' => KeyCode::KeyU, 'v' => KeyCode::KeyV, 'w' => KeyCode::KeyW, 'x' => KeyCode::KeyX,
+        'y' => KeyCode::KeyY, 'z' => KeyCode::KeyZ,
+        _ => return None,
+    };
+    Some(code)
+}
+
+fn stop_recording(recorder: Recorder) {
+    let path = recorder.path().display().to_string();
+    match recorder.finish() {
+        Ok(frames) => println!("Recorded {frames} frames to {path}"),
+        Err(err) => error!("Recording failed: {err}"),
+    }
+}
+
 fn log_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
     error!("{method_name}() failed: {err}");
     for source in err.sources().skip(1) {
@@ -317,19 +421,11 @@ impl World {
         }
     }
 
-    /// Draw the `World` state to the frame buffer.
+    /// Draw the `World` state to the frame buffer, smoothed by the phosphor history.
     ///
     /// Assumes the default texture format: `wgpu::TextureFormat::Rgba8UnormSrgb`
-    fn draw(&mut self, frame: &mut [u8]) {
-        for (i, pixel) in self.px.iter().enumerate() {
-            let mut rgba = [0xff, 0xff, 0xff, 0xff];
-            if *pixel == 0 {
-                rgba = [0x00, 0x00, 0x00, 0xff];
-            } 
-
-            let offset = i * 4;
-            frame[offset..offset + 4].copy_from_slice(&rgba);
-        }
+    pub fn draw(&mut self, frame: &mut [u8], palette: &Palette, phosphor: &Phosphor) {
+        phosphor.draw(&self.px, frame, palette);
         self.display_redraw = 0;
     }
 }
@@ -1,20 +1,480 @@
-use crate::{audio::Audio, cpu::get_cpu, display::{get_world, init_display}, instructions::{add_byte_to_reg, clear_screen, jump_inst, ld_mem_to_i, ld_to_reg}, memory::{get_font_arr, get_mem, init_fonts, read_memory, write_memory}};
-use std::{sync::{Arc, Mutex}, thread};
+use crate::{audio::{open_audio, AudioBackend, Synth, Tone, SAMPLES_PER_FRAME}, config::Config, error::EmuError, cache::{CachingBus, DecodeCache, Decoded, Handler}, cpu::{get_cpu, Reg, CPU}, hooks::{ReadOnly, Region, SelfModifying, Tracer, Watchpoints}, display::{get_world, init_display, init_display_picker, Session, World}, instructions::{add_byte_to_reg, add_op, add_to_i, and_op, call_addr, clear_screen, draw_sprite, dt_ld, jump_inst, jump_to_loc, ld_b, ld_dt, ld_f, ld_hf, ld_i, ld_mem_to_i, ld_reg_to_reg, ld_to_reg, ld_v, ld_vx_k, or_op, rand_op, ret, shl_op, shr_op, skip_next_eq, skip_next_eq_regs, skip_next_not_eq, sknp_op, skp_op, sne_op, st_ld, sub_op, subn_op, xor_op}, keyslog::{get_keyboard_map, Keyboard}, library::{prepare, RomFile}, memory::{init_fonts, Bus, Memory}, menu::Menu, platform::{Platform, PROGRAM_START}, recorder::Recorder, rom::Rom, screenshot::save_png, timing::{vip_cycles, Timing, VIP_CYCLES_PER_FRAME, VIP_DISPLAY_CYCLES}, tui::run_tui, watch::RomWatcher};
+use log::warn;
 
-pub fn init(contents: &mut Vec<u8>) {
-    let mut mem_arr = get_mem();
-    let mut fonts_arr = get_font_arr();
-    init_fonts(&mut mem_arr, &mut fonts_arr);
-    let mut cpu = get_cpu();
-    let mut audio = Audio::new().expect("Audio cannot be summoned");
-    let mem_idx = 0x200;
+/// Everything the emulated machine owns, independent of any front end.
+pub struct Machine {
+    pub cpu: CPU,
+    pub memory: Memory,
+    pub world: World,
+    pub keyboard: Keyboard,
+    /// Decoded instructions for `step_cached`.
+    pub cache: DecodeCache,
+    pub synth: Synth,
+    /// Instructions executed per 60 Hz frame, unless `timing` is `Timing::Vip`.
+    pub ipf: u32,
+    pub timing: Timing,
+    /// Machine cycles the VIP timing has run over into the next frame.
+    pub cycle_debt: i32,
+    /// The ROM image, loaded at 0x200 on every reset.
+    pub program: Vec<u8>,
+    /// Beeper output of the last frame run with `run_frame`.
+    pub samples: Vec<f32>,
+}
+
+pub fn get_machine(contents: &[u8], tone: Tone) -> Machine {
+    let mut machine = Machine {
+        cpu: get_cpu(),
+        memory: Memory::new(),
+        world: get_world(),
+        keyboard: get_keyboard_map(),
+        cache: DecodeCache::default(),
+        synth: Synth::new(tone),
+        ipf: 1,
+        timing: Timing::default(),
+        cycle_debt: 0,
+        program: contents.to_vec(),
+        samples: Vec::with_capacity(SAMPLES_PER_FRAME),
+    };
+    machine.reset();
+    machine
+}
+
+impl Machine {
+    /// Power the machine back on: a fresh CPU with the same settings, memory holding only
+    /// the fonts and the program, a clear screen and no keys down.
+    pub fn reset(&mut self) {
+        let (quirks, font, i_overflow) = (self.cpu.quirks, self.cpu.font, self.cpu.i_overflow);
+        self.cpu = get_cpu();
+        self.cpu.quirks = quirks;
+        self.cpu.font = font;
+        self.cpu.i_overflow = i_overflow;
+
+        self.memory.clear();
+        self.cache.clear();
+        init_fonts(&mut self.memory.bytes, font);
+        // Whatever does not fit in memory is dropped; ROM files are checked before this.
+        let len = self.program.len().min(Platform::Chip8.max_rom_size());
+        self.memory.bytes[PROGRAM_START..PROGRAM_START + len].copy_from_slice(&self.program[..len]);
+
+        self.world = get_world();
+        self.keyboard = get_keyboard_map();
+        self.cycle_debt = 0;
+    }
+
+    /// Replace the program and reset the machine to run it.
+    pub fn load(&mut self, contents: &[u8]) {
+        self.program = contents.to_vec();
+        self.reset();
+    }
+
+    /// Run one 60 Hz frame of instructions and render its beeper output into `samples`.
+    /// The beeper is on for the whole frame when the sound timer was running at its
+    /// start, so a beep lasts exactly as many frames as the value loaded into ST.
+    pub fn run_frame(&mut self) -> Result<(), EmuError> {
+        let gate = self.cpu.st > 0;
+        self.tick_timers();
+        match self.timing {
+            Timing::Ipf => {
+                for executed in 0..self.ipf {
+                    if self.waits_for_vblank(executed == 0) {
+                        break;
+                    }
+                    self.step_cached()?;
+                }
+            }
+            Timing::Vip => self.run_vip_frame()?,
+        }
 
-    for (i, &byte) in contents.iter().enumerate() {
-        write_memory(&mut mem_arr, mem_idx + i as u16, byte);
+        self.samples.clear();
+        for _ in 0..SAMPLES_PER_FRAME {
+            self.samples.push(self.synth.next_sample(gate));
+        }
+        Ok(())
     }
 
-    let mut world = get_world();
+    /// Run instructions for the cycles the VIP has left in a frame.
+    fn run_vip_frame(&mut self) -> Result<(), EmuError> {
+        let mut cycles = VIP_CYCLES_PER_FRAME - VIP_DISPLAY_CYCLES - self.cycle_debt;
+        let mut first = true;
+        while cycles > 0 {
+            if self.waits_for_vblank(first) {
+                // The rest of the frame is spent waiting.
+                cycles = 0;
+                break;
+            }
+            let opcode = self.next_opcode();
+            let pc = self.cpu.pc & 0xFFF;
+            let vx = self.cpu.regs[Reg::x(opcode)];
+            self.step_cached()?;
+            cycles -= vip_cycles(opcode, vx, self.cpu.pc == pc + 4);
+            first = false;
+        }
+        self.cycle_debt = (-cycles).max(0);
+        Ok(())
+    }
+
+    /// Whether the next instruction is a DXYN that has to wait for the next frame, as it
+    /// always does on the VIP and otherwise with the vblank quirk. One at the start of a
+    /// frame has just had its interrupt.
+    fn waits_for_vblank(&self, frame_start: bool) -> bool {
+        let vblank = self.timing == Timing::Vip || self.cpu.quirks.vblank;
+        vblank && !frame_start && self.next_opcode() & 0xF000 == 0xD000
+    }
+
+    /// The opcode at PC, read without notifying the memory hooks.
+    pub fn next_opcode(&self) -> u16 {
+        let pc = (self.cpu.pc & 0xFFF) as usize;
+        u16::from_be_bytes([self.memory.bytes[pc], self.memory.bytes[(pc + 1) & 0xFFF]])
+    }
 
-    let _ = init_display(&mut world, &mut mem_arr, &mut cpu, &mut audio).expect("Init display function failed");
+    /// Count the delay and sound timers down once, at 60 Hz.
+    pub fn tick_timers(&mut self) {
+        if self.cpu.dt > 0 {
+            self.cpu.dt -= 1;
+        }
 
+        if self.cpu.st > 0 {
+            self.cpu.st -= 1;
+        }
+    }
+
+    /// Fetch, decode and execute the next instruction, without the decode cache. This is
+    /// the reference path `step_cached` is measured against. A fault leaves the machine
+    /// at the faulting instruction. PC wraps around the end of memory, like I.
+    pub fn step(&mut self) -> Result<(), EmuError> {
+        self.cpu.pc &= 0xFFF;
+        let opcode = self.memory.fetch(self.cpu.pc);
+        decode(opcode)(self, opcode)
+    }
+
+    /// Execute the next instruction, decoding it only if its address was not run since
+    /// the memory there was last written.
+    pub fn step_cached(&mut self) -> Result<(), EmuError> {
+        self.cpu.pc &= 0xFFF;
+        let pc = self.cpu.pc;
+        let decoded = match self.cache.get(pc) {
+            Some(decoded) => {
+                self.memory.notify_fetch(pc);
+                decoded
+            }
+            None => {
+                let opcode = self.memory.fetch(pc);
+                let decoded = Decoded { opcode, handler: decode(opcode) };
+                self.cache.insert(pc, decoded);
+                decoded
+            }
+        };
+        (decoded.handler)(self, decoded.opcode)
+    }
+}
+
+/// A handler for an instruction that only touches the CPU.
+macro_rules! cpu_op {
+    ($op:ident) => {
+        |m, opcode| {
+            $op(&mut m.cpu, opcode);
+            Ok(())
+        }
+    };
+}
+
+/// The handler executing `opcode`. Unknown opcodes get one that logs a warning and
+/// leaves PC where it is.
+fn decode(opcode: u16) -> Handler {
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => |m, opcode| {                     // CLS
+                clear_screen(&mut m.world, &mut m.cpu, opcode);
+                Ok(())
+            },
+            0x00EE => |m, opcode| ret(&mut m.cpu, opcode), // RET
+            _ => |_, _| {
+                warn!("Unknown 0x0NNN opcode");
+                Ok(())
+            },
+        },
+        0x1000 => cpu_op!(jump_inst),                   // JP addr
+        0x2000 => |m, opcode| call_addr(&mut m.cpu, opcode), // CALL addr
+        0x3000 => cpu_op!(skip_next_eq),                // SE Vx, byte
+        0x4000 => cpu_op!(skip_next_not_eq),            // SNE Vx, byte
+        0x5000 => cpu_op!(skip_next_eq_regs),           // SE Vx, Vy
+        0x6000 => cpu_op!(ld_to_reg),                   // LD Vx, byte
+        0x7000 => cpu_op!(add_byte_to_reg),             // ADD Vx, byte
+        0x8000 => match opcode & 0x000F {
+            0x0 => cpu_op!(ld_reg_to_reg),              // LD Vx, Vy
+            0x1 => cpu_op!(or_op),                      // OR Vx, Vy
+            0x2 => cpu_op!(and_op),                     // AND Vx, Vy
+            0x3 => cpu_op!(xor_op),                     // XOR Vx, Vy
+            0x4 => cpu_op!(add_op),                     // ADD Vx, Vy
+            0x5 => cpu_op!(sub_op),                     // SUB Vx, Vy
+            0x6 => cpu_op!(shr_op),                     // SHR Vx {, Vy}
+            0x7 => cpu_op!(subn_op),                    // SUBN Vx, Vy
+            0xE => cpu_op!(shl_op),                     // SHL Vx {, Vy}
+            _ => |_, _| {
+                warn!("Unknown 0x8XY? opcode");
+                Ok(())
+            },
+        },
+        0x9000 => cpu_op!(sne_op),                      // SNE Vx, Vy
+        0xA000 => cpu_op!(ld_mem_to_i),                 // LD I, addr
+        0xB000 => cpu_op!(jump_to_loc),                 // JP V0, addr
+        0xC000 => cpu_op!(rand_op),                     // RND Vx, byte
+        0xD000 => |m, opcode| draw_sprite(&mut m.cpu, &mut m.memory, &mut m.world, opcode), // DRW Vx, Vy, nibble
+        0xE000 => match opcode & 0x00FF {
+            0x9E => |m, opcode| {                       // SKP Vx
+                skp_op(&mut m.cpu, opcode, &m.keyboard);
+                Ok(())
+            },
+            0xA1 => |m, opcode| {                       // SKNP Vx
+                sknp_op(&mut m.cpu, opcode, &m.keyboard);
+                Ok(())
+            },
+            _ => |_, _| {
+                warn!("Unknown 0xEX?? opcode");
+                Ok(())
+            },
+        },
+        0xF000 => match opcode & 0x00FF {
+            0x07 => cpu_op!(ld_dt),                     // LD Vx, DT
+            0x0A => |m, opcode| {                       // LD Vx, K
+                ld_vx_k(&mut m.cpu, opcode, &m.keyboard);
+                Ok(())
+            },
+            0x15 => cpu_op!(dt_ld),                     // LD DT, Vx
+            0x18 => cpu_op!(st_ld),                     // LD ST, Vx
+            0x1E => |m, opcode| add_to_i(&mut m.cpu, opcode), // ADD I, Vx
+            0x29 => cpu_op!(ld_f),                      // LD F, Vx
+            0x30 => cpu_op!(ld_hf),                     // LD HF, Vx
+            0x33 => |m, opcode| {                       // LD B, Vx
+                ld_b(&mut CachingBus { memory: &mut m.memory, cache: &mut m.cache }, &mut m.cpu, opcode)
+            },
+            0x55 => |m, opcode| {                       // LD [I], Vx
+                ld_i(&mut CachingBus { memory: &mut m.memory, cache: &mut m.cache }, &mut m.cpu, opcode)
+            },
+            0x65 => |m, opcode| {                       // LD Vx, [I]
+                ld_v(&mut CachingBus { memory: &mut m.memory, cache: &mut m.cache }, &mut m.cpu, opcode)
+            },
+            _ => |_, _| {
+                warn!("Unknown 0xFX?? opcode");
+                Ok(())
+            },
+        },
+        _ => unreachable!("Opcode {opcode:#06x} has a high nibble above 0xF"),
+    }
+}
+
+/// Run `frames` frames without opening a window, e.g. for screenshots in CI.
+pub fn run_headless(
+    machine: &mut Machine,
+    frames: u32,
+    mut recorder: Option<&mut Recorder>,
+    audio: &mut dyn AudioBackend,
+) -> Result<(), String> {
+    for _ in 0..frames {
+        machine.run_frame().map_err(|err| err.to_string())?;
+        if let Some(recorder) = recorder.as_deref_mut() {
+            recorder.capture(&machine.world)?;
+        }
+        audio.push(&machine.samples)?;
+    }
+    Ok(())
+}
+
+/// A machine running `contents` with the settings and memory hooks of `config`.
+fn new_machine(contents: &[u8], config: &Config) -> Machine {
+    let mut machine = configured_machine(contents, config);
+    install_hooks(&mut machine, config);
+    machine
+}
+
+/// Restart `machine` with a ROM reloaded by `--watch` and the settings found for it.
+/// The memory hooks are installed again only with `--keep-hooks`.
+pub fn reload(machine: &mut Machine, rom: &Rom, config: &Config) {
+    *machine = configured_machine(&rom.data, config);
+    if config.keep_hooks {
+        install_hooks(machine, config);
+    }
+}
+
+fn configured_machine(contents: &[u8], config: &Config) -> Machine {
+    let mut machine = get_machine(contents, config.tone);
+    machine.cpu.quirks = config.quirks;
+    machine.cpu.font = config.font;
+    machine.cpu.i_overflow = config.i_overflow;
+    machine.ipf = config.ipf;
+    machine.timing = config.timing;
+    machine.reset();
+    machine
+}
+
+/// Add the hooks of `--trace-memory`, `--watchpoint`, `--read-only` and `--flag-smc`.
+fn install_hooks(machine: &mut Machine, config: &Config) {
+    let len = machine.program.len();
+    let ranges = |regions: &[Region]| regions.iter().map(|region| region.addresses(len, config.font)).collect();
+    if config.trace_memory {
+        machine.memory.add_hook(Box::new(Tracer));
+    }
+    if !config.watchpoints.is_empty() {
+        machine.memory.add_hook(Box::new(Watchpoints { ranges: ranges(&config.watchpoints) }));
+    }
+    if !config.read_only.is_empty() {
+        machine.memory.add_hook(Box::new(ReadOnly { ranges: ranges(&config.read_only) }));
+    }
+    if config.flag_smc {
+        machine.memory.add_hook(Box::<SelfModifying>::default());
+    }
+}
+
+fn start_recorder(config: &Config) -> Option<Recorder> {
+    config.record.as_ref().map(|path| or_exit(Recorder::start(path, config.scale, &config.palette)))
+}
+
+/// The value of `result`, or report its error and exit with status 1.
+fn or_exit<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("Error: {err}");
+        std::process::exit(1);
+    })
+}
+
+pub fn init(args: &[String], file: &RomFile, contents: &[u8], config: &Config) {
+    let mut machine = new_machine(contents, config);
+    let mut recorder = start_recorder(config);
+
+    if let Some((frames, path)) = &config.screenshot_after {
+        let mut audio = or_exit(open_audio(false, config.mute, config.wav.as_deref()));
+        if let Err(err) = run_headless(&mut machine, *frames, recorder.as_mut(), audio.as_mut()) {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        }
+        or_exit(save_png(&machine.world, path, config.scale, &config.palette));
+        if let Some(recorder) = recorder {
+            or_exit(recorder.finish());
+        }
+        or_exit(audio.finish());
+        return;
+    }
+
+    let mut audio = or_exit(open_audio(true, config.mute, config.wav.as_deref()));
+    let watcher = config.watch.then(|| RomWatcher::new(file.clone(), args));
+
+    // A program fault ends the terminal front end; it has restored the terminal by then.
+    let result = if config.tui {
+        run_tui(&mut machine, audio.as_mut(), config, recorder, watcher)
+    } else {
+        let session = Session { machine, config: config.clone(), name: file.name.clone(), watcher };
+        init_display(session, audio.as_mut(), recorder).expect("Init display function failed");
+        Ok(())
+    };
+
+    if let Err(err) = audio.finish() {
+        eprintln!("Error: {err}");
+    }
+    if let Err(err) = result {
+        eprintln!("Error: {err}");
+        std::process::exit(1);
+    }
+}
+
+/// Let the user pick one of `roms` in the window and run it.
+pub fn init_picker(args: &[String], roms: Vec<RomFile>, config: &Config) {
+    let recorder = start_recorder(config);
+    let mut audio = or_exit(open_audio(true, config.mute, config.wav.as_deref()));
+
+    let menu = Menu::new(config.rom_path.clone(), roms.iter().map(|rom| rom.name.clone()).collect());
+    let mut load = |idx: usize| {
+        let file = &roms[idx];
+        let (config, rom) = prepare(args, config, file)?;
+        let watcher = config.watch.then(|| RomWatcher::new(file.clone(), args));
+        Ok(Session { machine: new_machine(&rom.data, &config), config, name: file.name.clone(), watcher })
+    };
+    init_display_picker(menu, config, &mut load, audio.as_mut(), recorder).expect("Init display function failed");
+
+    if let Err(err) = audio.finish() {
+        eprintln!("Error: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_options;
+    use crate::quirks::Quirks;
+
+    /// Cycles an ADD (7XNN) takes on the VIP, fetch included.
+    const ADD_CYCLES: i32 = 50;
+    /// Cycles the interpreter gets in a frame.
+    const BUDGET: i32 = VIP_CYCLES_PER_FRAME - VIP_DISPLAY_CYCLES;
+
+    fn vip_machine(rom: &[u8]) -> Machine {
+        let mut machine = get_machine(rom, Tone::default());
+        machine.timing = Timing::Vip;
+        machine
+    }
+
+    /// A long run of `7001`, adding 1 to V0.
+    fn adds() -> Vec<u8> {
+        [0x70, 0x01].repeat(200)
+    }
+
+    #[test]
+    fn vip_frame_runs_its_cycle_budget() {
+        let mut machine = vip_machine(&adds());
+        machine.run_frame().unwrap();
+        let executed = (BUDGET + ADD_CYCLES - 1) / ADD_CYCLES;
+        assert_eq!(machine.cpu.regs[Reg::new(0)] as i32, executed);
+        assert_eq!(machine.cycle_debt, executed * ADD_CYCLES - BUDGET);
+    }
+
+    #[test]
+    fn vip_cycle_debt_shortens_the_next_frame() {
+        let mut machine = vip_machine(&adds());
+        machine.cycle_debt = BUDGET - 2 * ADD_CYCLES;
+        machine.run_frame().unwrap();
+        assert_eq!(machine.cpu.regs[Reg::new(0)], 2);
+        assert_eq!(machine.cycle_debt, 0);
+    }
+
+    #[test]
+    fn vip_draws_one_sprite_per_frame_whatever_the_quirks() {
+        // DRW V0, V0, 1; ADD V0, 1; JP 0x200
+        let mut machine = vip_machine(&[0xD0, 0x01, 0x70, 0x01, 0x12, 0x00]);
+        machine.cpu.quirks = Quirks::NONE;
+        for frame in 1..=3 {
+            machine.run_frame().unwrap();
+            assert_eq!(machine.cpu.regs[Reg::new(0)], frame);
+        }
+    }
+
+    #[test]
+    fn reload_installs_the_hooks_again_only_when_kept() {
+        let mut config = parse_options(&["rom.ch8".to_string(), "--read-only".to_string(), "program".to_string()]).unwrap();
+        let rom = Rom::from_bytes(vec![0x12, 0x00, 0x00, 0xE0], Platform::Chip8).unwrap();
+        for keep_hooks in [true, false] {
+            config.keep_hooks = keep_hooks;
+            let mut machine = new_machine(&[0x12, 0x00], &config);
+            reload(&mut machine, &rom, &config);
+            // The program region follows the new ROM.
+            machine.memory.write(0x203, 0xFF);
+            assert_eq!(machine.memory.bytes[0x203], if keep_hooks { 0xE0 } else { 0xFF });
+            assert_eq!(machine.program, rom.data);
+        }
+    }
+
+    #[test]
+    fn vblank_quirk_holds_a_frame_to_one_sprite() {
+        // DRW V0, V0, 1; ADD V0, 1; JP 0x200
+        let mut machine = get_machine(&[0xD0, 0x01, 0x70, 0x01, 0x12, 0x00], Tone::default());
+        machine.ipf = 100;
+        machine.cpu.quirks = Quirks { vblank: true, ..Quirks::MODERN };
+        for frame in 1..=3 {
+            machine.run_frame().unwrap();
+            assert_eq!(machine.cpu.regs[Reg::new(0)], frame);
+        }
+
+        // Without it the 100 instructions go round the loop 33 times.
+        machine.cpu.quirks.vblank = false;
+        machine.run_frame().unwrap();
+        assert_eq!(machine.cpu.regs[Reg::new(0)], 3 + 33);
+    }
 }
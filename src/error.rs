@@ -0,0 +1,31 @@
+use std::fmt;
+
+use crate::cpu::STACK_DEPTH;
+
+/// A fault raised by the emulated program, which stops the machine.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EmuError {
+    /// The instruction at `pc` accessed `len` bytes at I, or added `len` to I, past the
+    /// end of memory with `--i-overflow fault`.
+    IOverflow { pc: u16, i: u16, len: u16 },
+    /// RET at `pc` with no subroutine to return from.
+    StackUnderflow { pc: u16 },
+    /// CALL at `pc` with `STACK_DEPTH` subroutines already running.
+    StackOverflow { pc: u16 },
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmuError::IOverflow { pc, i, len } => {
+                write!(f, "I overflow at {pc:#05x}: I = {i:#05x} plus {len} runs past 0xfff")
+            }
+            EmuError::StackUnderflow { pc } => write!(f, "Return at {pc:#05x} with an empty stack"),
+            EmuError::StackOverflow { pc } => {
+                write!(f, "Call at {pc:#05x} nests more than {STACK_DEPTH} subroutines")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EmuError {}
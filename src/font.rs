@@ -0,0 +1,258 @@
+use crate::platform::{Platform, PROGRAM_START};
+
+/// Bytes of the small font: sixteen 4x5 hex digits, 5 bytes each.
+pub const SMALL_FONT_SIZE: u16 = 16 * 5;
+/// Bytes of the big font: sixteen 8x10 hex digits, 10 bytes each.
+pub const BIG_FONT_SIZE: u16 = 16 * 10;
+/// Where the fonts are loaded unless `--font-addr` says otherwise.
+pub const DEFAULT_FONT_ADDR: u16 = 0x50;
+
+pub const FONT_NAMES: [&str; 5] = ["vip", "dream6800", "eti660", "schip", "octo"];
+
+/// The hex digit sprites of an interpreter, as read by FX29.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FontSet {
+    /// The COSMAC VIP interpreter.
+    Vip,
+    /// The DREAM 6800's CHIPOS.
+    Dream6800,
+    /// The ETI-660 interpreter.
+    Eti660,
+    /// SUPER-CHIP 1.1.
+    Schip,
+    /// Octo, and this emulator before fonts were selectable.
+    #[default]
+    Octo,
+}
+
+impl FontSet {
+    pub fn parse(name: &str) -> Result<FontSet, String> {
+        match name {
+            "vip" => Ok(FontSet::Vip),
+            "dream6800" => Ok(FontSet::Dream6800),
+            "eti660" => Ok(FontSet::Eti660),
+            "schip" => Ok(FontSet::Schip),
+            "octo" => Ok(FontSet::Octo),
+            _ => Err(format!("Unknown font: {name} (expected {})", FONT_NAMES.join(", "))),
+        }
+    }
+
+    /// The font of the platform's reference interpreter.
+    pub fn for_platform(platform: Platform) -> FontSet {
+        match platform {
+            Platform::Chip8 | Platform::XoChip => FontSet::Octo,
+            Platform::CosmacVip => FontSet::Vip,
+            Platform::SuperChip => FontSet::Schip,
+        }
+    }
+
+    pub fn small(self) -> &'static [u8; SMALL_FONT_SIZE as usize] {
+        match self {
+            FontSet::Vip => &VIP_FONT,
+            FontSet::Dream6800 => &DREAM6800_FONT,
+            FontSet::Eti660 => &ETI660_FONT,
+            FontSet::Schip | FontSet::Octo => &OCTO_FONT,
+        }
+    }
+}
+
+/// The font set and where it lives in memory. The small font starts at `addr` and the
+/// big font used by FX30 follows it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Font {
+    pub set: FontSet,
+    pub addr: u16,
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font { set: FontSet::default(), addr: DEFAULT_FONT_ADDR }
+    }
+}
+
+impl Font {
+    /// Check that both fonts fit below the program.
+    pub fn new(set: FontSet, addr: u16) -> Result<Font, String> {
+        let end = addr as usize + (SMALL_FONT_SIZE + BIG_FONT_SIZE) as usize;
+        if end > PROGRAM_START {
+            return Err(format!(
+                "Font address {addr:#05x} is too high: the fonts need {:#x} bytes below {PROGRAM_START:#05x}",
+                SMALL_FONT_SIZE + BIG_FONT_SIZE
+            ));
+        }
+        Ok(Font { set, addr })
+    }
+
+    /// Address of the small sprite for the low nibble of `digit`.
+    pub fn small_digit(self, digit: u8) -> u16 {
+        self.addr + (digit & 0xF) as u16 * 5
+    }
+
+    /// Address of the big sprite for the low nibble of `digit`.
+    pub fn big_digit(self, digit: u8) -> u16 {
+        self.addr + SMALL_FONT_SIZE + (digit & 0xF) as u16 * 10
+    }
+
+    /// Both fonts, laid out as they are loaded at `addr`.
+    pub fn bytes(self) -> Vec<u8> {
+        let mut bytes = self.set.small().to_vec();
+        bytes.extend_from_slice(&BIG_FONT);
+        bytes
+    }
+}
+
+const VIP_FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+const DREAM6800_FONT: [u8; 80] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x40, 0x40, 0x40, 0x40, 0x40, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+const ETI660_FONT: [u8; 80] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x20, 0x20, 0x20, 0x20, 0x20, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0x80, 0x80, 0xE0, 0xA0, 0xE0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0x20, 0x20, 0xE0, 0xA0, 0xE0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+/// Also the SUPER-CHIP 1.1 small font.
+const OCTO_FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// The SUPER-CHIP 1.1 big digits 0-9, with Octo's A-F for the digits SUPER-CHIP lacks.
+const BIG_FONT: [u8; 160] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::Tone;
+    use crate::cpu::Reg;
+    use crate::emu8::get_machine;
+    use crate::memory::init_fonts;
+
+    const SETS: [FontSet; 5] = [FontSet::Vip, FontSet::Dream6800, FontSet::Eti660, FontSet::Schip, FontSet::Octo];
+
+    #[test]
+    fn fonts_end_below_the_program() {
+        let highest = PROGRAM_START as u16 - SMALL_FONT_SIZE - BIG_FONT_SIZE;
+        assert_eq!(Font::new(FontSet::Vip, highest), Ok(Font { set: FontSet::Vip, addr: highest }));
+        assert!(Font::new(FontSet::Vip, highest + 1).is_err());
+        assert!(Font::new(FontSet::Vip, 0).is_ok());
+    }
+
+    #[test]
+    fn digits_point_at_their_sprites() {
+        for (set, name) in SETS.into_iter().zip(FONT_NAMES) {
+            assert_eq!(FontSet::parse(name), Ok(set));
+            let font = Font::new(set, 0x20).unwrap();
+            let mut memory = [0; 4096];
+            init_fonts(&mut memory, font);
+            for digit in 0..16u8 {
+                let small = font.small_digit(digit) as usize;
+                assert_eq!(small, 0x20 + digit as usize * 5);
+                assert_eq!(memory[small..small + 5], set.small()[digit as usize * 5..][..5]);
+                let big = font.big_digit(digit) as usize;
+                assert_eq!(big, 0x20 + 80 + digit as usize * 10);
+                assert_eq!(memory[big..big + 10], BIG_FONT[digit as usize * 10..][..10]);
+            }
+            // Only the low nibble of VX selects the digit.
+            assert_eq!(font.small_digit(0x1A), font.small_digit(0xA));
+            assert_eq!(font.big_digit(0xF3), font.big_digit(0x3));
+        }
+    }
+
+    #[test]
+    fn fx29_and_fx30_use_the_selected_font() {
+        // LD F, V0; LD HF, V1
+        let mut machine = get_machine(&[0xF0, 0x29, 0xF1, 0x30], Tone::default());
+        machine.cpu.font = Font::new(FontSet::Dream6800, 0x100).unwrap();
+        machine.reset();
+        (machine.cpu.regs[Reg::new(0)], machine.cpu.regs[Reg::new(1)]) = (0x7, 0xC);
+
+        machine.step().unwrap();
+        let i = machine.cpu.regs.i as usize;
+        assert_eq!(i, 0x100 + 7 * 5);
+        assert_eq!(machine.memory.bytes[i..i + 5], DREAM6800_FONT[7 * 5..][..5]);
+
+        machine.step().unwrap();
+        let i = machine.cpu.regs.i as usize;
+        assert_eq!(i, 0x100 + 80 + 0xC * 10);
+        assert_eq!(machine.memory.bytes[i..i + 10], BIG_FONT[0xC * 10..][..10]);
+    }
+}
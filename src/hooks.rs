@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+use log::{debug, info, warn};
+
+use crate::font::{Font, BIG_FONT_SIZE, SMALL_FONT_SIZE};
+use crate::memory::MemoryHook;
+use crate::platform::PROGRAM_START;
+
+/// A range of memory named on the command line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Region {
+    /// 0x000-0x1FF, where the original interpreter lived.
+    Reserved,
+    /// The small and big hex digit sprites.
+    Font,
+    /// The loaded ROM image.
+    Program,
+    Range(u16, u16),
+}
+
+impl Region {
+    /// Parse `reserved`, `font`, `program`, an address like `0x300` or a range like
+    /// `0x300-0x30f`.
+    pub fn parse(value: &str) -> Result<Region, String> {
+        let parse_addr = |addr: &str| {
+            u16::from_str_radix(addr.trim().trim_start_matches("0x"), 16)
+                .ok()
+                .filter(|&addr| addr < 0x1000)
+                .ok_or(format!("Invalid address: {addr} (expected hex between 0x000 and 0xfff)"))
+        };
+        match value {
+            "reserved" => Ok(Region::Reserved),
+            "font" => Ok(Region::Font),
+            "program" => Ok(Region::Program),
+            _ => match value.split_once('-') {
+                Some((start, end)) => match (parse_addr(start)?, parse_addr(end)?) {
+                    (start, end) if start > end => Err(format!("Invalid range: {value} (the start is past the end)")),
+                    (start, end) => Ok(Region::Range(start, end)),
+                },
+                None => parse_addr(value).map(|addr| Region::Range(addr, addr)),
+            },
+        }
+    }
+
+    /// The addresses of the region for a program of `program_len` bytes and the fonts
+    /// loaded as `font`.
+    pub fn addresses(self, program_len: usize, font: Font) -> RangeInclusive<u16> {
+        match self {
+            Region::Reserved => 0x000..=0x1FF,
+            Region::Font => font.addr..=font.addr + SMALL_FONT_SIZE + BIG_FONT_SIZE - 1,
+            Region::Program => PROGRAM_START as u16..=(PROGRAM_START + program_len.max(1) - 1) as u16,
+            Region::Range(start, end) => start..=end,
+        }
+    }
+}
+
+/// Logs every memory access at debug level.
+pub struct Tracer;
+
+impl MemoryHook for Tracer {
+    fn read(&mut self, addr: u16, value: u8) {
+        debug!("read  {addr:#05x} = {value:#04x}");
+    }
+
+    fn write(&mut self, addr: u16, value: u8) -> bool {
+        debug!("write {addr:#05x} = {value:#04x}");
+        true
+    }
+
+    fn fetch(&mut self, addr: u16) {
+        debug!("fetch {addr:#05x}");
+    }
+}
+
+/// Reports reads and writes of watched addresses.
+pub struct Watchpoints {
+    pub ranges: Vec<RangeInclusive<u16>>,
+}
+
+impl Watchpoints {
+    fn watched(&self, addr: u16) -> bool {
+        self.ranges.iter().any(|range| range.contains(&addr))
+    }
+}
+
+impl MemoryHook for Watchpoints {
+    fn read(&mut self, addr: u16, value: u8) {
+        if self.watched(addr) {
+            info!("Watchpoint: read {value:#04x} from {addr:#05x}");
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) -> bool {
+        if self.watched(addr) {
+            info!("Watchpoint: write {value:#04x} to {addr:#05x}");
+        }
+        true
+    }
+}
+
+/// Drops writes into protected ranges.
+pub struct ReadOnly {
+    pub ranges: Vec<RangeInclusive<u16>>,
+}
+
+impl MemoryHook for ReadOnly {
+    fn write(&mut self, addr: u16, value: u8) -> bool {
+        let protected = self.ranges.iter().any(|range| range.contains(&addr));
+        if protected {
+            warn!("Read-only: blocked write of {value:#04x} to {addr:#05x}");
+        }
+        !protected
+    }
+}
+
+/// Flags writes to addresses that have already been executed, i.e. self-modifying code.
+#[derive(Default)]
+pub struct SelfModifying {
+    executed: HashSet<u16>,
+}
+
+impl MemoryHook for SelfModifying {
+    fn write(&mut self, addr: u16, value: u8) -> bool {
+        // Forget the address once reported, so each patch of executed code is reported once.
+        if self.executed.remove(&addr) {
+            warn!("Self-modifying code: write {value:#04x} to executed address {addr:#05x}");
+        }
+        true
+    }
+
+    fn fetch(&mut self, addr: u16) {
+        self.executed.insert(addr);
+        self.executed.insert((addr + 1) & 0xFFF);
+    }
+
+    fn reset(&mut self) {
+        self.executed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::font::FontSet;
+    use crate::memory::{Bus, Memory};
+
+    thread_local! {
+        static REPORTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Keeps the messages logged on each test's thread.
+    struct Capture;
+
+    impl log::Log for Capture {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            REPORTS.with(|reports| reports.borrow_mut().push(record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// The info and warning messages logged while `run` accesses memory with `hook` installed.
+    fn reports(hook: Box<dyn MemoryHook>, run: impl FnOnce(&mut Memory)) -> (Memory, Vec<String>) {
+        let _ = log::set_logger(&Capture);
+        log::set_max_level(log::LevelFilter::Info);
+        REPORTS.with(|reports| reports.borrow_mut().clear());
+        let mut memory = Memory::new();
+        memory.add_hook(hook);
+        run(&mut memory);
+        (memory, REPORTS.with(|reports| reports.take()))
+    }
+
+    #[test]
+    fn parses_regions() {
+        assert_eq!(Region::parse("reserved"), Ok(Region::Reserved));
+        assert_eq!(Region::parse("font"), Ok(Region::Font));
+        assert_eq!(Region::parse("program"), Ok(Region::Program));
+        assert_eq!(Region::parse("0x300"), Ok(Region::Range(0x300, 0x300)));
+        assert_eq!(Region::parse("0x300-0x30f"), Ok(Region::Range(0x300, 0x30F)));
+        assert_eq!(Region::parse("300 - 30F"), Ok(Region::Range(0x300, 0x30F)));
+        assert!(Region::parse("0x1000").is_err());
+        assert!(Region::parse("stack").is_err());
+        assert!(Region::parse("0x300-0x200").is_err());
+    }
+
+    #[test]
+    fn region_addresses() {
+        let font = Font::new(FontSet::Octo, 0x50).unwrap();
+        assert_eq!(Region::Reserved.addresses(10, font), 0x000..=0x1FF);
+        assert_eq!(Region::Font.addresses(10, font), 0x50..=0x50 + SMALL_FONT_SIZE + BIG_FONT_SIZE - 1);
+        assert_eq!(Region::Program.addresses(10, font), 0x200..=0x209);
+    }
+
+    #[test]
+    fn read_only_drops_and_reports_writes() {
+        let (memory, reports) = reports(Box::new(ReadOnly { ranges: vec![0x300..=0x30F] }), |memory| {
+            memory.write(0x2FF, 1);
+            memory.write(0x300, 2);
+            memory.write(0x30F, 3);
+        });
+        assert_eq!(memory.bytes[0x2FF..=0x300], [1, 0]);
+        assert_eq!(memory.bytes[0x30F], 0);
+        assert_eq!(reports, [
+            "Read-only: blocked write of 0x02 to 0x300",
+            "Read-only: blocked write of 0x03 to 0x30f",
+        ]);
+    }
+
+    #[test]
+    fn watchpoints_report_reads_and_writes() {
+        let (memory, reports) = reports(Box::new(Watchpoints { ranges: vec![0x300..=0x301] }), |memory| {
+            memory.write(0x301, 7);
+            memory.write(0x302, 8);
+            memory.read(0x301);
+            memory.read(0x2FF);
+        });
+        assert_eq!(memory.bytes[0x302], 8);
+        assert_eq!(reports, ["Watchpoint: write 0x07 to 0x301", "Watchpoint: read 0x07 from 0x301"]);
+    }
+
+    #[test]
+    fn self_modifying_reports_writes_to_executed_code() {
+        let (memory, reports) = reports(Box::<SelfModifying>::default(), |memory| {
+            memory.fetch(0x200);
+            memory.write(0x204, 1);
+            memory.write(0x201, 2);
+            memory.write(0x201, 3);
+            // An opcode at 0xFFF ends at 0x000.
+            memory.fetch(0xFFF);
+            memory.write(0x000, 4);
+        });
+        assert_eq!(memory.bytes[0x201], 3);
+        assert_eq!(reports, [
+            "Self-modifying code: write 0x02 to executed address 0x201",
+            "Self-modifying code: write 0x04 to executed address 0x000",
+        ]);
+    }
+}
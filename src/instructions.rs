@@ -1,330 +1,236 @@
 use rand::Rng;
 
-use crate::{cpu::{get_reg_val, set_reg_val, CPU}, display::{self, World}, memory::{read_memory, write_memory}};
+use crate::{cpu::{Reg, CPU, STACK_DEPTH}, display::{self, World}, error::EmuError, keyslog::Keyboard, memory::Bus, quirks::IOverflow};
 
 pub fn jump_inst(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Jump instruction");
-    println!("Current opcode is {:04x}", opcode);
-    println!("Jump addr is {:04x}", opcode & 0xFFF);
     cpu.pc = opcode & 0xFFF;
 }
 
-pub fn ret(cpu: &mut CPU, opcode: u16) {
-    println!("Executing RET instruction");
-    cpu.pc = cpu.sp.pop().expect("Should return stack pointer value");
+pub fn ret(cpu: &mut CPU, _opcode: u16) -> Result<(), EmuError> {
+    cpu.pc = cpu.sp.pop().ok_or(EmuError::StackUnderflow { pc: cpu.pc })?;
     cpu.pc += 2;
+    Ok(())
 }
 
 pub fn jump_to_loc(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Jump to location instruction");
-    println!("Current opcode is {:04x}", opcode);
-    println!("Jump addr is {:04x}", opcode & 0xFFF);
-    cpu.pc = (opcode & 0xFFF) + cpu.regs.v0.value as u16;
+    // With the jump quirk BXNN adds VX, the register named by the top nibble of the address.
+    let reg = if cpu.quirks.jump { Reg::x(opcode) } else { Reg::new(0) };
+    cpu.pc = (opcode & 0xFFF) + cpu.regs[reg] as u16;
 }
 
 pub fn rand_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing RND instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    let low_byte = 0xFF & opcode;
-    println!("Our low byte (value to write) is: {:02x}", low_byte);
     let mut rng = rand::rng();
-    let val: u8 = rng.random_range(0..=255);
-    println!("Our random value to write is: {:02x}", val);
-    set_reg_val(cpu, val, reg_byte as u8);
+    let val: u8 = rng.random_range(0..=255) & opcode as u8;
+    cpu.regs[Reg::x(opcode)] = val;
     cpu.pc += 2;
 }
 
-pub fn skp_op(cpu: &mut CPU, opcode: u16, keyboard: &mut std::collections::HashMap<u8, u8>) {
-    println!("Executing SKP instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    let reg_x_val = get_reg_val(cpu, reg_byte as u8);
-    if keyboard[&reg_x_val] > 0 {
+pub fn skp_op(cpu: &mut CPU, opcode: u16, keyboard: &Keyboard) {
+    let reg_x_val = cpu.regs[Reg::x(opcode)];
+    if keyboard[(reg_x_val & 0xF) as usize] > 0 {
         cpu.pc += 2;
     }
     cpu.pc += 2;
 }
 
-pub fn sknp_op(cpu: &mut CPU, opcode: u16, keyboard: &mut std::collections::HashMap<u8, u8>) {
-    println!("Executing SKNP instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    let reg_x_val = get_reg_val(cpu, reg_byte as u8);
-    if keyboard[&reg_x_val] == 0 {
+pub fn sknp_op(cpu: &mut CPU, opcode: u16, keyboard: &Keyboard) {
+    let reg_x_val = cpu.regs[Reg::x(opcode)];
+    if keyboard[(reg_x_val & 0xF) as usize] == 0 {
         cpu.pc += 2;
     }
     cpu.pc += 2;
 }
 
 pub fn ld_to_reg(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Load instruction");
-    println!("Current opcode is {:04x}", opcode);
-
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
     let low_byte = 0xFF & opcode;
-    println!("Our low byte (value to write) is: {:02x}", low_byte);
-    set_reg_val(cpu, low_byte as u8, reg_byte as u8);
+    cpu.regs[Reg::x(opcode)] = low_byte as u8;
     cpu.pc += 2;
 }
 
 pub fn ld_dt(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Load DT instruction");
-    println!("Current opcode is {:04x}", opcode);
-
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    println!("Our DT value is: {:02x}", cpu.dt);
-    set_reg_val(cpu, cpu.dt as u8, reg_byte as u8);
+    cpu.regs[Reg::x(opcode)] = cpu.dt;
     cpu.pc += 2;
 }
 
 pub fn dt_ld(cpu: &mut CPU, opcode: u16) {
-    println!("Executing DT LD instruction");
-    println!("Current opcode is {:04x}", opcode);
-
-    println!("Our DT value is: {:02x}", cpu.dt);
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    let reg_x_val = get_reg_val(cpu, reg_byte as u8);
-    println!("Our reg value to load in dt is: {reg_x_val}");
+    let reg_x_val = cpu.regs[Reg::x(opcode)];
     
     cpu.dt = reg_x_val;
     cpu.pc += 2;
 }
 
 pub fn st_ld(cpu: &mut CPU, opcode: u16) {
-    println!("Executing ST LD instruction");
-    println!("Current opcode is {:04x}", opcode);
-
-    println!("Our ST value is: {:02x}", cpu.st);
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    let reg_x_val = get_reg_val(cpu, reg_byte as u8);
-    println!("Our reg value to load in dt is: {reg_x_val}");
+    let reg_x_val = cpu.regs[Reg::x(opcode)];
     
     cpu.st = reg_x_val;
     cpu.pc += 2;
 }
 
 pub fn ld_reg_to_reg(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Load instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
-    set_reg_val(cpu, y_reg_val as u8, x_reg as u8);
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
+    cpu.regs[Reg::x(opcode)] = y_reg_val;
     cpu.pc += 2;
 }
 
 pub fn ld_mem_to_i(cpu: &mut CPU, opcode: u16) {
-    println!("Executing load memory to I REG instruction");
-    println!("Current opcode is {:04x}", opcode);
-
     let val = opcode & 0xFFF;
 
-    println!("Addr value to write in I Reg: {:04x}", val);
-
-    cpu.regs.i.value = val;
+    cpu.regs.i = val;
     cpu.pc += 2;
 }
 
 pub fn add_byte_to_reg(cpu: &mut CPU, opcode: u16) {
-    println!("Executing ADD byte to REG");
-    println!("Current opcode is {:04x}", opcode);
-
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
+    let reg_x = Reg::x(opcode);
     let low_byte = 0xFF & opcode;
-    println!("Our low byte is: {:02x}", low_byte);
-    let (val_to_write, _carry) = get_reg_val(cpu, reg_byte as u8).overflowing_add(low_byte as u8);
-    println!("Our value to write is: {:02x}", val_to_write);
-    set_reg_val(cpu, val_to_write as u8, reg_byte as u8);
+    let (val_to_write, _carry) = cpu.regs[reg_x].overflowing_add(low_byte as u8);
+    cpu.regs[reg_x] = val_to_write;
     cpu.pc += 2;
 }
 
-pub fn add_to_i(cpu: &mut CPU, opcode: u16) {
-    println!("Executing ADD to I");
-    println!("Current opcode is {:04x}", opcode);
-
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    let reg_x_val = get_reg_val(cpu, reg_byte as u8);
-    println!("Our reg value is: {reg_x_val}");
-    cpu.regs.i.value = unsafe { cpu.regs.i.value } + reg_x_val as u16;
+pub fn add_to_i(cpu: &mut CPU, opcode: u16) -> Result<(), EmuError> {
+    let reg_x_val = cpu.regs[Reg::x(opcode)];
+    let sum = cpu.regs.i + reg_x_val as u16;
+    let overflow = sum > 0xFFF;
+    match cpu.i_overflow {
+        IOverflow::Fault if overflow => {
+            return Err(EmuError::IOverflow { pc: cpu.pc, i: cpu.regs.i, len: reg_x_val as u16 });
+        }
+        IOverflow::Flag => cpu.regs[Reg::VF] = overflow as u8,
+        _ => {}
+    }
+    cpu.regs.i = sum & 0xFFF;
     cpu.pc += 2;
+    Ok(())
 }
 
 pub fn ld_f(cpu: &mut CPU, opcode: u16) {
-    println!("Executing LD F to I");
-    println!("Current opcode is {:04x}", opcode);
-
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    let reg_x_val = get_reg_val(cpu, reg_byte as u8);
-    println!("Our reg value is: {reg_x_val}");
-    cpu.regs.i.value = 0x50 + (reg_x_val * 5) as u16;
+    let reg_x_val = cpu.regs[Reg::x(opcode)];
+    cpu.regs.i = cpu.font.small_digit(reg_x_val);
     cpu.pc += 2;
 }
 
-pub fn ld_b(mem_arr: &mut [u8; 4096], cpu: &mut CPU, opcode: u16) {
-    println!("Executing LD B to I");
-    println!("Current opcode is {:04x}", opcode);
+pub fn ld_hf(cpu: &mut CPU, opcode: u16) {
+    let reg_x_val = cpu.regs[Reg::x(opcode)];
+    cpu.regs.i = cpu.font.big_digit(reg_x_val);
+    cpu.pc += 2;
+}
 
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-    let reg_x_val = get_reg_val(cpu, reg_byte as u8);
-    println!("Our reg value is: {reg_x_val}");
+pub fn ld_b(bus: &mut impl Bus, cpu: &mut CPU, opcode: u16) -> Result<(), EmuError> {
+    let reg_x_val = cpu.regs[Reg::x(opcode)];
     let first_digit = reg_x_val / 100;
     let second_digit = (reg_x_val % 100) / 10;
     let third_digit = reg_x_val % 10;
-    println!("First digit to write: {first_digit}");
-    println!("Second digit to write: {second_digit}");
-    println!("Third digit to write: {third_digit}");
-    write_memory(mem_arr, unsafe { cpu.regs.i.value }, first_digit);
-    write_memory(mem_arr, unsafe { cpu.regs.i.value } + 1, second_digit);
-    write_memory(mem_arr, unsafe { cpu.regs.i.value } + 2, third_digit);
+    for (addr, digit) in i_addrs(cpu, 3)?.zip([first_digit, second_digit, third_digit]) {
+        bus.write(addr, digit);
+    }
     cpu.pc += 2;
+    Ok(())
 }
 
-pub fn ld_i(mem_arr: &mut [u8; 4096], cpu: &mut CPU, opcode: u16) {
-    println!("Executing LD I to I");
-    println!("Current opcode is {:04x}", opcode);
-
+pub fn ld_i(bus: &mut impl Bus, cpu: &mut CPU, opcode: u16) -> Result<(), EmuError> {
     let reg_byte = (0x0F00 & opcode) >> 8;
-    for i in 0..=reg_byte {
-        let reg_str = "v".to_owned() + &i.to_string();
-        println!("Our reg to load is: {reg_str}");
-        let val = get_reg_val(cpu, i as u8);
-        println!("Our value from reg is: {val}");
-        let addr = unsafe { cpu.regs.i.value } + i as u16;
-        println!("Our address to write in: {addr}");
-        write_memory(mem_arr, addr, val);
+    for (i, addr) in i_addrs(cpu, reg_byte + 1)?.enumerate() {
+        let val = cpu.regs[Reg::new(i as u8)];
+        bus.write(addr, val);
     }
+    advance_i(cpu, reg_byte);
     cpu.pc += 2;
+    Ok(())
 }
 
-pub fn ld_v(mem_arr: &mut [u8; 4096], cpu: &mut CPU, opcode: u16) {
-    println!("Executing LD V to I");
-    println!("Current opcode is {:04x}", opcode);
-
+pub fn ld_v(bus: &mut impl Bus, cpu: &mut CPU, opcode: u16) -> Result<(), EmuError> {
     let reg_byte = (0x0F00 & opcode) >> 8;
-    for i in 0..=reg_byte {
-        let addr = unsafe { cpu.regs.i.value } + i as u16;
-        println!("Our address to read from: {addr}");
-        let val = read_memory(mem_arr, addr);
-        let reg_str = "v".to_owned() + &i.to_string();
-        println!("Our reg to write in is: {reg_str}");
-        set_reg_val(cpu, val, i as u8);
+    for (i, addr) in i_addrs(cpu, reg_byte + 1)?.enumerate() {
+        let val = bus.read(addr);
+        cpu.regs[Reg::new(i as u8)] = val;
     }
+    advance_i(cpu, reg_byte);
     cpu.pc += 2;
+    Ok(())
 }
 
-pub fn ld_vx_k(cpu: &mut CPU, opcode: u16, keyboard: &mut std::collections::HashMap<u8,u8>) {
-    println!("Executing LD Vx K");
-    println!("Current opcode is {:04x}", opcode);
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to load is: {reg_str}");
-
-    // Debug: print the current keyboard map
-    println!("Current keyboard map: {:?}", keyboard);
-
-    // Find if any key is pressed (value != 0)
-    if let Some((&key, &val)) = keyboard.iter().find(|(_, &v)| v != 0) {
-        println!("Key pressed: {key} (value: {val})");
-        set_reg_val(cpu, key, reg_byte as u8);
-        cpu.pc += 2; // Advance to next instruction
-    } else {
-        println!("No key pressed, waiting...");
-        // Do not advance pc, so this instruction will be retried
+/// Move I past the registers stored or loaded by FX55/FX65, as the quirks ask.
+fn advance_i(cpu: &mut CPU, reg_byte: u16) {
+    if cpu.quirks.memory_leave_i_unchanged {
+        return;
     }
-    cpu.pc += 2; // Advance to next instruction
+    let step = if cpu.quirks.memory_increment_by_x { reg_byte } else { reg_byte + 1 };
+    cpu.regs.i = (cpu.regs.i + step) & 0xFFF;
 }
 
+/// The `len` addresses starting at I, wrapped at 4 KiB. With `IOverflow::Fault`, a range
+/// running past the end of memory is refused before any of it is accessed, so the
+/// faulting instruction has no effect.
+fn i_addrs(cpu: &CPU, len: u16) -> Result<impl Iterator<Item = u16>, EmuError> {
+    let i = cpu.regs.i;
+    if cpu.i_overflow == IOverflow::Fault && i + len > 0x1000 {
+        return Err(EmuError::IOverflow { pc: cpu.pc, i, len });
+    }
+    Ok((i..i + len).map(|addr| addr & 0xFFF))
+}
+
+pub fn ld_vx_k(cpu: &mut CPU, opcode: u16, keyboard: &Keyboard) {
 
-pub fn clear_screen(world: &mut World, cpu: &mut CPU, opcode: u16) {
-    println!("Executing Clear Screen");
-    println!("Current opcode is {:04x}", opcode);
+    // Without a key down PC stays here, so the instruction is retried until one is.
+    if let Some(key) = keyboard.iter().position(|&v| v != 0) {
+        cpu.regs[Reg::x(opcode)] = key as u8;
+        cpu.pc += 2;
+    }
+}
 
+pub fn clear_screen(world: &mut World, cpu: &mut CPU, _opcode: u16) {
     world.px = [0; (display::CHIP8_WIDTH * display::CHIP8_HEIGHT) as usize];
     world.display_redraw = 1;
     cpu.pc += 2;
 }
 
-pub fn draw_sprite(cpu: &mut CPU, mem_arr: &[u8], world: &mut World, opcode: u16) {
-    println!("Executing Draw Sprite");
-    println!("Current opcode is {:04x}", opcode);
+pub fn draw_sprite(cpu: &mut CPU, bus: &mut impl Bus, world: &mut World, opcode: u16) -> Result<(), EmuError> {
     let bytes_to_read = 0xf & (0xFF & opcode);
-    println!("Our Bytes to read: {:02x}", bytes_to_read);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
-    let i_reg_val = unsafe { cpu.regs.i.value };
-    let sprite_slice = &mem_arr[i_reg_val as usize..i_reg_val as usize + bytes_to_read as usize];
-    cpu.regs.vf.value = 0;
-    for (rows, byte) in sprite_slice.iter().enumerate() {
+    let x_reg_val = cpu.regs[Reg::x(opcode)];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
+    let addrs = i_addrs(cpu, bytes_to_read)?;
+    cpu.regs[Reg::VF] = 0;
+    for (rows, addr) in addrs.enumerate() {
+        let byte = bus.read(addr);
         for bit in 0..8 {
             let pixel = byte >> (7 - bit) & 1;
-            let x = (x_reg_val as usize + bit) % display::CHIP8_WIDTH as usize;
-            let y = (y_reg_val as usize + rows) % display::CHIP8_HEIGHT as usize;
+            let x = x_reg_val as usize % display::CHIP8_WIDTH as usize + bit;
+            let y = y_reg_val as usize % display::CHIP8_HEIGHT as usize + rows;
+            // The start position always wraps; pixels past the edge wrap or are clipped.
+            if !cpu.quirks.wrap && (x >= display::CHIP8_WIDTH as usize || y >= display::CHIP8_HEIGHT as usize) {
+                continue;
+            }
+            let x = x % display::CHIP8_WIDTH as usize;
+            let y = y % display::CHIP8_HEIGHT as usize;
             let idx = y * display::CHIP8_WIDTH as usize + x;
 
             let old_px = world.px[idx];
             world.px[idx] ^= pixel;
             
             if old_px == 1 && world.px[idx] == 0 {
-                cpu.regs.vf.value = 1;
+                cpu.regs[Reg::VF] = 1;
             }
         }
     }
 
     world.display_redraw = 1;
     cpu.pc += 2;
+    Ok(())
 }
 
-pub fn call_addr(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Call instruction");
-    println!("Current opcode is {:04x}", opcode);
-    println!("Call addr is {:04x}", opcode & 0xFFF);
+pub fn call_addr(cpu: &mut CPU, opcode: u16) -> Result<(), EmuError> {
+    if cpu.sp.len() == STACK_DEPTH {
+        return Err(EmuError::StackOverflow { pc: cpu.pc });
+    }
     let addr = opcode & 0xFFF;
     cpu.sp.push(cpu.pc);
     cpu.pc = addr;
+    Ok(())
 }
 
 pub fn skip_next_eq(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Skip instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to compare is: {reg_str}");
-    let val_to_compare = get_reg_val(cpu, reg_byte as u8);
-    println!("Our value to compare is: {val_to_compare}");
-    let low_byte = 0xFF & opcode as u8;
-    println!("Our low byte (value to compare) is: {:02x}", low_byte);
+    let val_to_compare = cpu.regs[Reg::x(opcode)];
+    let low_byte = opcode as u8;
     if val_to_compare == low_byte {
         cpu.pc += 2;
     }
@@ -332,15 +238,8 @@ pub fn skip_next_eq(cpu: &mut CPU, opcode: u16) {
 }
 
 pub fn skip_next_not_eq(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Skip instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let reg_byte = (0x0F00 & opcode) >> 8;
-    let reg_str = "v".to_owned() + &reg_byte.to_string();
-    println!("Our reg to compare is: {reg_str}");
-    let val_to_compare = get_reg_val(cpu, reg_byte as u8);
-    println!("Our value to compare is: {val_to_compare}");
-    let low_byte = 0xFF & opcode as u8;
-    println!("Our low byte (value to compare) is: {:02x}", low_byte);
+    let val_to_compare = cpu.regs[Reg::x(opcode)];
+    let low_byte = opcode as u8;
     if val_to_compare != low_byte {
         cpu.pc += 2;
     }
@@ -348,16 +247,8 @@ pub fn skip_next_not_eq(cpu: &mut CPU, opcode: u16) {
 }
 
 pub fn skip_next_eq_regs(cpu: &mut CPU, opcode: u16) {
-    println!("Executing Skip instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
+    let x_reg_val = cpu.regs[Reg::x(opcode)];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
     if x_reg_val == y_reg_val {
         cpu.pc += 2;
     }
@@ -365,164 +256,251 @@ pub fn skip_next_eq_regs(cpu: &mut CPU, opcode: u16) {
 }
 
 pub fn or_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing OR instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
+    let reg_x = Reg::x(opcode);
+    let x_reg_val = cpu.regs[reg_x];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
     let val = x_reg_val | y_reg_val;
-    println!("Our Value to write is: {:02x}", val);
-    set_reg_val(cpu, val as u8, x_reg as u8);
+    cpu.regs[reg_x] = val;
+    if cpu.quirks.logic {
+        cpu.regs[Reg::VF] = 0;
+    }
     cpu.pc += 2;
 }
 
 pub fn and_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing AND instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
+    let reg_x = Reg::x(opcode);
+    let x_reg_val = cpu.regs[reg_x];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
     let val = x_reg_val & y_reg_val;
-    println!("Our Value to write is: {:02x}", val);
-    set_reg_val(cpu, val as u8, x_reg as u8);
+    cpu.regs[reg_x] = val;
+    if cpu.quirks.logic {
+        cpu.regs[Reg::VF] = 0;
+    }
     cpu.pc += 2;
 }
 
 pub fn xor_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing XOR instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
+    let reg_x = Reg::x(opcode);
+    let x_reg_val = cpu.regs[reg_x];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
     let val = x_reg_val ^ y_reg_val;
-    println!("Our Value to write is: {:02x}", val);
-    set_reg_val(cpu, val as u8, x_reg as u8);
+    cpu.regs[reg_x] = val;
+    if cpu.quirks.logic {
+        cpu.regs[Reg::VF] = 0;
+    }
     cpu.pc += 2;
 }
 
 pub fn add_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing ADD Logical instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
+    let reg_x = Reg::x(opcode);
+    let x_reg_val = cpu.regs[reg_x];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
     let (result, carry) = x_reg_val.overflowing_add(y_reg_val);
-    cpu.regs.vf.value = if carry { 1 } else { 0 };
-    println!("Our Value to write is: {:02x}", result);
-    set_reg_val(cpu, result, x_reg as u8);
+    // VF is written last, so it holds the flag even when it is also VX.
+    cpu.regs[reg_x] = result;
+    cpu.regs[Reg::VF] = carry as u8;
     cpu.pc += 2;
 } 
 
 pub fn sub_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing SUB Logical instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
-    let (result, carry) = x_reg_val.overflowing_sub(y_reg_val);
-    cpu.regs.vf.value = if carry { 1 } else { 0 };
-    println!("Our Value to write is: {:02x}", result);
-    set_reg_val(cpu, result as u8, x_reg as u8);
+    let reg_x = Reg::x(opcode);
+    let x_reg_val = cpu.regs[reg_x];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
+    let (result, borrow) = x_reg_val.overflowing_sub(y_reg_val);
+    cpu.regs[reg_x] = result;
+    cpu.regs[Reg::VF] = !borrow as u8;
     cpu.pc += 2;
 } 
 
 pub fn subn_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing SUBN Logical instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
-    if y_reg_val > x_reg_val {
-        cpu.regs.vf.value = 1;
-    } else {
-        cpu.regs.vf.value = 0;
-    }
-    let val = y_reg_val - x_reg_val;
-    println!("Our Value to write is: {:02x}", val);
-    set_reg_val(cpu, val as u8, x_reg as u8);
+    let reg_x = Reg::x(opcode);
+    let x_reg_val = cpu.regs[reg_x];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
+    let (result, borrow) = y_reg_val.overflowing_sub(x_reg_val);
+    cpu.regs[reg_x] = result;
+    cpu.regs[Reg::VF] = !borrow as u8;
     cpu.pc += 2;
 } 
 
 pub fn shr_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing SHR Logical instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
-    cpu.regs.vf.value = x_reg_val & 0x1;
+    let reg_x = Reg::x(opcode);
+    let x_reg_val = cpu.regs[reg_x];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
+    let x_reg_val = if cpu.quirks.shift { x_reg_val } else { y_reg_val };
     let val = x_reg_val >> 1;
-
-    println!("Our Value to write is: {:02x}", val);
-    set_reg_val(cpu, val as u8, x_reg as u8);
+    cpu.regs[reg_x] = val;
+    cpu.regs[Reg::VF] = x_reg_val & 0x1;
     cpu.pc += 2;
 } 
 
 pub fn shl_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing SHL Logical instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
-    cpu.regs.vf.value = (x_reg_val & 0x80) >> 7;
+    let reg_x = Reg::x(opcode);
+    let x_reg_val = cpu.regs[reg_x];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
+    let x_reg_val = if cpu.quirks.shift { x_reg_val } else { y_reg_val };
     let val = x_reg_val << 1;
-    println!("Our Value to write is: {:02x}", val);
-    set_reg_val(cpu, val as u8, x_reg as u8);
+    cpu.regs[reg_x] = val;
+    cpu.regs[Reg::VF] = (x_reg_val & 0x80) >> 7;
     cpu.pc += 2;
 } 
 
 pub fn sne_op(cpu: &mut CPU, opcode: u16) {
-    println!("Executing SNE Logical instruction");
-    println!("Current opcode is {:04x}", opcode);
-    let y_reg = (0x00F0 & opcode) >> 4 as u8;
-    println!("Our VY Reg: {:02x}", y_reg);
-    let x_reg = (0x0F00 & opcode) >> 8;
-    println!("Our VX Reg: {:02x}", x_reg);
-    let x_reg_val = get_reg_val(cpu, x_reg as u8);
-    println!("Our VX Reg Value: {:02x}", x_reg_val);
-    let y_reg_val = get_reg_val(cpu, y_reg as u8);
-    println!("Our VY Reg Value: {:02x}", y_reg_val);
+    let x_reg_val = cpu.regs[Reg::x(opcode)];
+    let y_reg_val = cpu.regs[Reg::y(opcode)];
     if x_reg_val != y_reg_val {
         cpu.pc += 2;
     } 
     cpu.pc += 2;
 } 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::get_cpu;
+    use crate::display::get_world;
+    use crate::memory::Memory;
+    use crate::quirks::Quirks;
+
+    fn cpu_at_i(i: u16, mode: IOverflow) -> CPU {
+        let mut cpu = get_cpu();
+        cpu.regs.i = i;
+        cpu.i_overflow = mode;
+        cpu
+    }
+
+    #[test]
+    fn add_to_i_wraps() {
+        let mut cpu = cpu_at_i(0xFFE, IOverflow::Wrap);
+        cpu.regs[Reg::new(0)] = 4;
+        cpu.regs[Reg::VF] = 7;
+        add_to_i(&mut cpu, 0xF01E).unwrap();
+        assert_eq!(cpu.regs.i, 0x002);
+        assert_eq!(cpu.regs[Reg::VF], 7);
+    }
+
+    #[test]
+    fn add_to_i_faults() {
+        let mut cpu = cpu_at_i(0xFFE, IOverflow::Fault);
+        cpu.regs[Reg::new(0)] = 4;
+        assert_eq!(add_to_i(&mut cpu, 0xF01E), Err(EmuError::IOverflow { pc: 0x200, i: 0xFFE, len: 4 }));
+        assert_eq!((cpu.regs.i, cpu.pc), (0xFFE, 0x200));
+
+        cpu.regs[Reg::new(0)] = 1;
+        add_to_i(&mut cpu, 0xF01E).unwrap();
+        assert_eq!(cpu.regs.i, 0xFFF);
+    }
+
+    #[test]
+    fn add_to_i_flag_sets_vf_on_overflow() {
+        let mut cpu = cpu_at_i(0xFFE, IOverflow::Flag);
+        cpu.regs[Reg::new(0)] = 4;
+        add_to_i(&mut cpu, 0xF01E).unwrap();
+        assert_eq!((cpu.regs.i, cpu.regs[Reg::VF]), (0x002, 1));
+
+        add_to_i(&mut cpu, 0xF01E).unwrap();
+        assert_eq!((cpu.regs.i, cpu.regs[Reg::VF]), (0x006, 0));
+    }
+
+    #[test]
+    fn ld_b_wraps() {
+        for mode in [IOverflow::Wrap, IOverflow::Flag] {
+            let mut memory = Memory::new();
+            let mut cpu = cpu_at_i(0xFFE, mode);
+            cpu.regs[Reg::new(0)] = 123;
+            ld_b(&mut memory, &mut cpu, 0xF033).unwrap();
+            assert_eq!([memory.bytes[0xFFE], memory.bytes[0xFFF], memory.bytes[0x000]], [1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn ld_b_faults_without_writing() {
+        let mut memory = Memory::new();
+        let mut cpu = cpu_at_i(0xFFE, IOverflow::Fault);
+        cpu.regs[Reg::new(0)] = 123;
+        assert_eq!(ld_b(&mut memory, &mut cpu, 0xF033), Err(EmuError::IOverflow { pc: 0x200, i: 0xFFE, len: 3 }));
+        assert!(memory.bytes.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn ld_i_wraps() {
+        for mode in [IOverflow::Wrap, IOverflow::Flag] {
+            let mut memory = Memory::new();
+            let mut cpu = cpu_at_i(0xFFF, mode);
+            cpu.regs.v[..2].copy_from_slice(&[0xAA, 0xBB]);
+            ld_i(&mut memory, &mut cpu, 0xF155).unwrap();
+            assert_eq!([memory.bytes[0xFFF], memory.bytes[0x000]], [0xAA, 0xBB]);
+        }
+    }
+
+    #[test]
+    fn ld_i_faults_without_writing() {
+        let mut memory = Memory::new();
+        let mut cpu = cpu_at_i(0xFFF, IOverflow::Fault);
+        cpu.regs.v[..2].copy_from_slice(&[0xAA, 0xBB]);
+        assert!(ld_i(&mut memory, &mut cpu, 0xF155).is_err());
+        assert!(memory.bytes.iter().all(|&byte| byte == 0));
+
+        ld_i(&mut memory, &mut cpu, 0xF055).unwrap();
+        assert_eq!(memory.bytes[0xFFF], 0xAA);
+    }
+
+    #[test]
+    fn ld_v_wraps() {
+        for mode in [IOverflow::Wrap, IOverflow::Flag] {
+            let mut memory = Memory::new();
+            memory.bytes[0xFFF] = 0xAA;
+            memory.bytes[0x000] = 0xBB;
+            let mut cpu = cpu_at_i(0xFFF, mode);
+            ld_v(&mut memory, &mut cpu, 0xF165).unwrap();
+            assert_eq!(cpu.regs.v[..2], [0xAA, 0xBB]);
+        }
+    }
+
+    #[test]
+    fn ld_v_faults_without_loading() {
+        let mut memory = Memory::new();
+        memory.bytes[0xFFF] = 0xAA;
+        let mut cpu = cpu_at_i(0xFFF, IOverflow::Fault);
+        assert!(ld_v(&mut memory, &mut cpu, 0xF165).is_err());
+        assert_eq!((cpu.regs[Reg::new(0)], cpu.pc), (0, 0x200));
+    }
+
+    #[test]
+    fn advance_i_wraps() {
+        let mut memory = Memory::new();
+        let mut cpu = cpu_at_i(0xFFE, IOverflow::Wrap);
+        cpu.quirks = Quirks::VIP;
+        ld_i(&mut memory, &mut cpu, 0xF155).unwrap();
+        assert_eq!(cpu.regs.i, 0x000);
+    }
+
+    #[test]
+    fn draw_sprite_wraps() {
+        for mode in [IOverflow::Wrap, IOverflow::Flag] {
+            let mut memory = Memory::new();
+            memory.bytes[0xFFF] = 0x80;
+            memory.bytes[0x000] = 0x40;
+            let mut world = get_world();
+            let mut cpu = cpu_at_i(0xFFF, mode);
+            draw_sprite(&mut cpu, &mut memory, &mut world, 0xD002).unwrap();
+            let width = display::CHIP8_WIDTH as usize;
+            assert_eq!((world.px[0], world.px[width + 1]), (1, 1));
+        }
+    }
+
+    #[test]
+    fn draw_sprite_faults_without_drawing() {
+        let mut memory = Memory::new();
+        memory.bytes[0xFFF] = 0xFF;
+        let mut world = get_world();
+        let mut cpu = cpu_at_i(0xFFF, IOverflow::Fault);
+        assert_eq!(
+            draw_sprite(&mut cpu, &mut memory, &mut world, 0xD002),
+            Err(EmuError::IOverflow { pc: 0x200, i: 0xFFF, len: 2 })
+        );
+        assert!(world.px.iter().all(|&px| px == 0));
+
+        draw_sprite(&mut cpu, &mut memory, &mut world, 0xD001).unwrap();
+        assert_eq!(world.px[..8], [1; 8]);
+    }
+}
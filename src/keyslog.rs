@@ -1,20 +1,26 @@
-pub fn get_keyboard_map() -> std::collections::HashMap<u8, u8> {
-    let mut keyboard = std::collections::HashMap::new();
-    keyboard.insert(0, 0);
-    keyboard.insert(1, 0);
-    keyboard.insert(2, 0);
-    keyboard.insert(3, 0);
-    keyboard.insert(4, 0);
-    keyboard.insert(5, 0);
-    keyboard.insert(6, 0);
-    keyboard.insert(7, 0);
-    keyboard.insert(8, 0);
-    keyboard.insert(9, 0);
-    keyboard.insert(0xA, 0);
-    keyboard.insert(0xB, 0);
-    keyboard.insert(0xC, 0);
-    keyboard.insert(0xD, 0);
-    keyboard.insert(0xE, 0);
-    keyboard.insert(0xF, 0);
-    return keyboard;
+/// Key states of the CHIP-8 keypad, indexed by key; nonzero while the key is down.
+pub type Keyboard = [u8; 16];
+
+pub fn get_keyboard_map() -> Keyboard {
+    [0; 16]
+}
+
+/// Host keys for CHIP-8 keys 0x0..=0xF, so that the COSMAC VIP keypad
+///
+/// ```text
+/// 1 2 3 C
+/// 4 5 6 D
+/// 7 8 9 E
+/// A 0 B F
+/// ```
+///
+/// lands on the `1234` / `QWER` / `ASDF` / `ZXCV` block of a QWERTY keyboard.
+pub const DEFAULT_LAYOUT: [char; 16] = [
+    'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+];
+
+/// Find the CHIP-8 key a host character is mapped to.
+pub fn chip8_key(layout: &[char; 16], c: char) -> Option<u8> {
+    let c = c.to_ascii_lowercase();
+    layout.iter().position(|&k| k == c).map(|key| key as u8)
 }
@@ -0,0 +1,28 @@
+#![forbid(unsafe_code)]
+
+pub mod display;
+pub mod emu8;
+pub mod cpu;
+pub mod memory;
+pub mod cache;
+pub mod audio;
+pub mod instructions;
+pub mod keyslog;
+pub mod config;
+pub mod screenshot;
+pub mod recorder;
+pub mod palette;
+pub mod phosphor;
+pub mod tui;
+pub mod platform;
+pub mod rom;
+pub mod quirks;
+pub mod romdb;
+pub mod library;
+pub mod menu;
+pub mod watch;
+pub mod turbo;
+pub mod hooks;
+pub mod font;
+pub mod error;
+pub mod timing;
@@ -0,0 +1,138 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::config::{parse_args_for_rom, Config};
+use crate::rom::{read_rom, sha1_hex, Rom, RomError};
+use crate::romdb::RomDb;
+
+/// File extensions listed when a directory or zip archive is opened.
+pub const ROM_EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+
+/// Where a ROM image is stored.
+#[derive(Clone)]
+pub enum RomLocation {
+    File(PathBuf),
+    Zip { archive: PathBuf, entry: String },
+}
+
+/// A ROM found on the command line, in a directory or in a zip archive.
+#[derive(Clone)]
+pub struct RomFile {
+    /// Path relative to the directory or archive, or the file name.
+    pub name: String,
+    pub location: RomLocation,
+}
+
+impl RomFile {
+    /// The file holding the ROM, which is the archive for ROMs inside a zip.
+    pub fn path(&self) -> &Path {
+        match &self.location {
+            RomLocation::File(path) => path,
+            RomLocation::Zip { archive, .. } => archive,
+        }
+    }
+
+    pub fn read(&self) -> Result<Vec<u8>, RomError> {
+        match &self.location {
+            RomLocation::File(path) => read_rom(path),
+            RomLocation::Zip { archive, entry } => {
+                read_zip_entry(archive, entry).map_err(|e| RomError::Io(format!("{}: {e}", archive.display())))
+            }
+        }
+    }
+}
+
+/// List the ROMs at `path`: the file itself, or the ROM files inside a zip archive or
+/// a directory and its subdirectories, sorted by name.
+pub fn list_roms(path: &Path) -> Result<Vec<RomFile>, String> {
+    let mut roms = if path.is_dir() {
+        let mut roms = Vec::new();
+        scan_dir(path, path, &mut roms).map_err(|e| format!("{}: {e}", path.display()))?;
+        roms
+    } else if is_zip(path) {
+        scan_zip(path).map_err(|e| format!("{}: {e}", path.display()))?
+    } else {
+        let name = path.file_name().map_or(path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        return Ok(vec![RomFile { name, location: RomLocation::File(path.to_path_buf()) }]);
+    };
+
+    roms.sort_by_key(|rom| rom.name.to_lowercase());
+    Ok(roms)
+}
+
+fn has_rom_extension(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ROM_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+fn is_zip(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && magic == *b"PK\x03\x04"
+}
+
+fn scan_dir(root: &Path, dir: &Path, roms: &mut Vec<RomFile>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            scan_dir(root, &path, roms)?;
+        } else if has_rom_extension(&path.to_string_lossy()) {
+            let name = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().into_owned();
+            roms.push(RomFile { name, location: RomLocation::File(path) });
+        }
+    }
+    Ok(())
+}
+
+fn scan_zip(path: &Path) -> Result<Vec<RomFile>, zip::result::ZipError> {
+    let zip = zip::ZipArchive::new(File::open(path)?)?;
+    Ok(zip
+        .file_names()
+        .filter(|name| !name.ends_with('/') && has_rom_extension(name))
+        .map(|name| RomFile {
+            name: name.to_string(),
+            location: RomLocation::Zip { archive: path.to_path_buf(), entry: name.to_string() },
+        })
+        .collect())
+}
+
+fn read_zip_entry(archive: &Path, entry: &str) -> Result<Vec<u8>, zip::result::ZipError> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+    let mut data = Vec::new();
+    zip.by_name(entry)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Read a ROM, look it up in the program database, parse the options again with its
+/// settings and validate it for the resulting platform.
+pub fn prepare(args: &[String], config: &Config, file: &RomFile) -> Result<(Config, Rom), String> {
+    let data = file.read().map_err(|err| format!("cannot load {}: {err}", file.name))?;
+
+    let sha1 = sha1_hex(&data);
+    let db = match &config.rom_db {
+        _ if config.no_rom_db => None,
+        Some(path) => match RomDb::load(path) {
+            Ok(db) => Some(db),
+            Err(err) => {
+                eprintln!("Warning: cannot read program database {err}");
+                None
+            }
+        },
+        None => None,
+    };
+    let entry = db.and_then(|db| db.lookup(&sha1));
+    if let Some(entry) = &entry {
+        println!("Recognised: {}", entry.title);
+    }
+    let rom_args = entry.map(|entry| entry.to_args()).unwrap_or_default();
+    let config = parse_args_for_rom(args, &sha1, &rom_args)?;
+
+    let rom = Rom::from_bytes(data, config.platform).map_err(|err| format!("cannot load {}: {err}", file.name))?;
+    println!("ROM: {} bytes, SHA-1 {}, CRC32 {:08x}", rom.data.len(), rom.sha1, rom.crc32);
+    if rom.detected != config.platform.instruction_set() {
+        eprintln!("Warning: this looks like a {} program, but the platform is {}", rom.detected, config.platform);
+    }
+    Ok((config, rom))
+}
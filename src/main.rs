@@ -1,41 +1,59 @@
-use std::fs::{File};
+#![forbid(unsafe_code)]
+
 use std::{env};
-use std::io::Read;
-
-use crate::keyslog::get_keyboard_map;
-use crate::cpu::get_cpu;
-use crate::display::{get_world, init_display};
-use crate::emu8::init;
-use crate::instructions::{add_byte_to_reg, clear_screen, jump_inst, ld_mem_to_i, ld_to_reg};
-use crate::memory::{get_mem, read_memory, write_memory};
-mod display;
-mod emu8;
-mod cpu;
-mod memory;
-mod audio;
-mod instructions;
-mod keyslog;
+use std::path::Path;
+
+use chip8emu::config::parse_args;
+use chip8emu::emu8::{init, init_picker};
+use chip8emu::library::{list_roms, prepare, ROM_EXTENSIONS};
 
 fn main() -> std::io::Result<()> {
+    // Memory hook reports are shown unless RUST_LOG says otherwise.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("error,chip8emu::hooks=info")).init();
     println!("Program started");
 
     let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 || args[1].is_empty() {
-        eprintln!("Error: No filepath argument provided or argument is empty.");
-        panic!();
-    }
-    let filepath = &args[1];
+    let config = match parse_args(&args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        }
+    };
+    let filepath = config.rom_path.clone();
 
     println!("Filepath: {filepath}");
-    
-    let mut file = File::open(filepath).expect("Failed to open a file");
-    println!("File found");
 
-    let mut contents = Vec::new();
+    let roms = match list_roms(Path::new(&filepath)) {
+        Ok(roms) if roms.is_empty() => {
+            eprintln!("Error: no ROMs ({}) found in {filepath}", ROM_EXTENSIONS.map(|ext| format!(".{ext}")).join(", "));
+            std::process::exit(1);
+        }
+        Ok(roms) => roms,
+        Err(err) => {
+            eprintln!("Error: cannot load {filepath}: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    if roms.len() > 1 {
+        if config.tui || config.screenshot_after.is_some() {
+            eprintln!("Error: {filepath} holds {} ROMs; choosing one needs the window front end", roms.len());
+            std::process::exit(1);
+        }
+        init_picker(&args, roms, &config);
+        return Ok(());
+    }
 
-    file.read_to_end(&mut contents).expect("Failed to read file to the end");
+    let (config, rom) = match prepare(&args, &config, &roms[0]) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        }
+    };
 
-    init(&mut contents);
+    init(&args, &roms[0], &rom.data, &config);
 
     Ok(())
 }
@@ -1,3 +1,5 @@
+use crate::font::Font;
+
 pub fn read_memory(mem_arr: &[u8; 4096], addr: u16) -> u8 {
     mem_arr[addr as usize]
 }
@@ -6,32 +8,105 @@ pub fn write_memory(mem_arr: &mut [u8; 4096], addr: u16, value: u8) {
     mem_arr[addr as usize] = value;
 }
 
-pub fn get_font_arr() -> [u8; 80] {
-    [
-        0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-        0x20, 0x60, 0x20, 0x20, 0x70, // 1
-        0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-        0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-        0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-        0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-        0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-        0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-        0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-        0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-        0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-        0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-        0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-        0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-        0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-        0xF0, 0x80, 0xF0, 0x80, 0x80  // F
-    ]
-}
-pub fn init_fonts(mem_arr: &mut [u8; 4096], fonts_arr: &mut [u8; 80]) {
-    for (i, e) in fonts_arr.iter().enumerate() {
-        mem_arr[0x50 + i] = *e;
+/// Load both fonts at their configured address.
+pub fn init_fonts(mem_arr: &mut [u8; 4096], font: Font) {
+    for (i, e) in font.bytes().iter().enumerate() {
+        mem_arr[font.addr as usize + i] = *e;
     }
 }
 
 pub fn get_mem() -> [u8; 4096] {
     [0; 4096]
 }
+
+/// Memory as the CPU sees it. Instructions read and write through a bus so that hooks
+/// can observe or refuse accesses.
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, value: u8);
+    /// Read the opcode at `addr` to execute it.
+    fn fetch(&mut self, addr: u16) -> u16;
+}
+
+/// Observes the accesses made through a `Memory` bus, e.g. watchpoints, tracers and
+/// write protection. All methods default to doing nothing.
+pub trait MemoryHook {
+    fn read(&mut self, _addr: u16, _value: u8) {}
+
+    /// Called before `value` is written to `addr`; returning false drops the write.
+    fn write(&mut self, _addr: u16, _value: u8) -> bool {
+        true
+    }
+
+    /// Called when the opcode at `addr` is fetched for execution.
+    fn fetch(&mut self, _addr: u16) {}
+
+    /// Called when the machine is reset.
+    fn reset(&mut self) {}
+}
+
+/// The 4 KiB of CHIP-8 memory with the hooks watching it.
+pub struct Memory {
+    /// Raw contents, for loading programs and inspecting state without triggering hooks.
+    pub bytes: [u8; 4096],
+    hooks: Vec<Box<dyn MemoryHook>>,
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory { bytes: get_mem(), hooks: Vec::new() }
+    }
+
+    pub fn add_hook(&mut self, hook: Box<dyn MemoryHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Tell the hooks the opcode at `addr` is executed, when it was not read to do so.
+    pub fn notify_fetch(&mut self, addr: u16) {
+        for hook in &mut self.hooks {
+            hook.fetch(addr);
+        }
+    }
+
+    /// Zero the contents and reset the hooks, which stay installed.
+    pub fn clear(&mut self) {
+        self.bytes = get_mem();
+        for hook in &mut self.hooks {
+            hook.reset();
+        }
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Memory {
+        Memory::new()
+    }
+}
+
+impl Bus for Memory {
+    fn read(&mut self, addr: u16) -> u8 {
+        let value = read_memory(&self.bytes, addr);
+        for hook in &mut self.hooks {
+            hook.read(addr, value);
+        }
+        value
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        let mut allowed = true;
+        for hook in &mut self.hooks {
+            allowed &= hook.write(addr, value);
+        }
+        if allowed {
+            write_memory(&mut self.bytes, addr, value);
+        }
+    }
+
+    /// An opcode at 0xFFF takes its low byte from 0x000.
+    fn fetch(&mut self, addr: u16) -> u16 {
+        self.notify_fetch(addr);
+        let hi: u16 = (read_memory(&self.bytes, addr) as u16) << 8;
+        let lo: u16 = read_memory(&self.bytes, (addr + 1) & 0xFFF) as u16;
+        hi | lo
+    }
+}
@@ -0,0 +1,154 @@
+use crate::display::{CHIP8_HEIGHT, CHIP8_WIDTH};
+use crate::palette::Palette;
+
+/// The menu is drawn at this multiple of the CHIP-8 resolution, so it fills the same
+/// window with smaller pixels.
+pub const MENU_SCALE: u32 = 5;
+pub const MENU_WIDTH: u32 = CHIP8_WIDTH * MENU_SCALE;
+pub const MENU_HEIGHT: u32 = CHIP8_HEIGHT * MENU_SCALE;
+
+const GLYPH_WIDTH: usize = 4;
+const LINE_HEIGHT: usize = 7;
+const COLUMNS: usize = MENU_WIDTH as usize / GLYPH_WIDTH - 2;
+/// Lines left for ROM names below the title and above the status line.
+const VISIBLE: usize = MENU_HEIGHT as usize / LINE_HEIGHT - 4;
+
+/// Keys understood by the menu.
+pub enum MenuKey {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+/// Keyboard driven list of ROM names.
+pub struct Menu {
+    pub title: String,
+    pub items: Vec<String>,
+    pub selected: usize,
+    /// Shown on the bottom line, e.g. why the last ROM could not be loaded.
+    pub status: String,
+    scroll: usize,
+}
+
+impl Menu {
+    pub fn new(title: String, items: Vec<String>) -> Menu {
+        Menu { title, items, selected: 0, status: "Up/Down to choose, Enter to run, Esc to quit".to_string(), scroll: 0 }
+    }
+
+    pub fn key(&mut self, key: MenuKey) {
+        let last = self.items.len().saturating_sub(1);
+        self.selected = match key {
+            MenuKey::Up => self.selected.saturating_sub(1),
+            MenuKey::Down => (self.selected + 1).min(last),
+            MenuKey::PageUp => self.selected.saturating_sub(VISIBLE),
+            MenuKey::PageDown => (self.selected + VISIBLE).min(last),
+            MenuKey::Home => 0,
+            MenuKey::End => last,
+        };
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + VISIBLE {
+            self.scroll = self.selected + 1 - VISIBLE;
+        }
+    }
+
+    /// Draw the menu into an RGBA frame of `MENU_WIDTH` x `MENU_HEIGHT` pixels. The
+    /// selected line is drawn in inverted colors.
+    pub fn draw(&self, frame: &mut [u8], palette: &Palette) {
+        let (bg, fg) = (palette.color(0), palette.color(1));
+        for pixel in frame.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&bg);
+        }
+
+        draw_text(frame, 1, 0, &self.title, fg, bg);
+        let rows = self.items.iter().enumerate().skip(self.scroll).take(VISIBLE);
+        for (row, (idx, name)) in rows.enumerate() {
+            let (text, back) = if idx == self.selected { (bg, fg) } else { (fg, bg) };
+            draw_text(frame, 0, row + 2, &format!(" {name:COLUMNS$} "), text, back);
+        }
+        draw_text(frame, 1, VISIBLE + 3, &self.status, fg, bg);
+    }
+}
+
+/// Draw a line of text at a character column and line, cutting it at the right edge.
+fn draw_text(frame: &mut [u8], column: usize, line: usize, text: &str, fg: [u8; 4], bg: [u8; 4]) {
+    let width = MENU_WIDTH as usize;
+    for (i, c) in text.chars().enumerate().take(width / GLYPH_WIDTH - column) {
+        let rows = glyph(c);
+        let left = (column + i) * GLYPH_WIDTH;
+        let top = line * LINE_HEIGHT;
+        for y in 0..LINE_HEIGHT {
+            for x in 0..GLYPH_WIDTH {
+                // Glyphs are 3x5, placed one pixel down in a 4x7 cell.
+                let lit = (1..6).contains(&y) && x < 3 && rows[y - 1] >> (2 - x) & 1 == 1;
+                let idx = ((top + y) * width + left + x) * 4;
+                if let Some(pixel) = frame.get_mut(idx..idx + 4) {
+                    pixel.copy_from_slice(if lit { &fg } else { &bg });
+                }
+            }
+        }
+    }
+}
+
+/// 3x5 bitmap of a character, one row of three bits per entry. Lowercase letters use
+/// the uppercase shapes and characters without a glyph are drawn as `?`.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        '(' | '[' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' | ']' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '&' => [0b010, 0b101, 0b010, 0b101, 0b011],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
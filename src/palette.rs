@@ -0,0 +1,96 @@
+/// Colors used to present `World.px`, indexed by pixel value: 0 is the background,
+/// 1 the first plane, 2 the second plane and 3 both planes overlapping.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Palette {
+    pub colors: [[u8; 4]; 4],
+}
+
+pub const PALETTE_NAMES: [&str; 5] = ["classic", "green", "amber", "lcd", "octo"];
+
+impl Palette {
+    pub fn color(&self, pixel: u8) -> [u8; 4] {
+        self.colors[(pixel & 3) as usize]
+    }
+
+    /// Look up one of the built-in palettes by name.
+    pub fn named(name: &str) -> Option<Palette> {
+        let hex = match name {
+            "classic" => [0x000000, 0xffffff, 0xaaaaaa, 0x555555],
+            "green" => [0x0a1a0a, 0x33ff66, 0x1f9e40, 0x66ff99],
+            "amber" => [0x1a0f00, 0xffb000, 0x9e6d00, 0xffd060],
+            "lcd" => [0x9bbc0f, 0x0f380f, 0x306230, 0x8bac0f],
+            "octo" => [0x996600, 0xffcc00, 0xff6600, 0x662200],
+            _ => return None,
+        };
+        Some(Palette { colors: hex.map(rgba) })
+    }
+
+    /// Parse a comma separated list of 2 to 4 hex colors, e.g. `#000000,#33ff66`.
+    /// Colors that are left out repeat the first plane color.
+    pub fn parse(list: &str) -> Result<Palette, String> {
+        let parsed = list.split(',').map(parse_hex).collect::<Result<Vec<_>, _>>()?;
+        if !(2..=4).contains(&parsed.len()) {
+            return Err(format!("Expected 2 to 4 colors, got {}: {list}", parsed.len()));
+        }
+
+        let mut colors = [parsed[1]; 4];
+        colors[..parsed.len()].copy_from_slice(&parsed);
+        Ok(Palette { colors })
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::named("classic").unwrap()
+    }
+}
+
+fn parse_hex(color: &str) -> Result<[u8; 4], String> {
+    let digits = color.trim().trim_start_matches('#');
+    match u32::from_str_radix(digits, 16) {
+        Ok(value) if digits.len() == 6 => Ok(rgba(value)),
+        _ => Err(format!("Invalid color, expected RRGGBB hex: {color}")),
+    }
+}
+
+fn rgba(hex: u32) -> [u8; 4] {
+    [(hex >> 16) as u8, (hex >> 8) as u8, hex as u8, 0xff]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_listed_name_is_a_palette() {
+        for name in PALETTE_NAMES {
+            assert!(Palette::named(name).is_some(), "{name}");
+        }
+        assert_eq!(Palette::named("classic").unwrap().color(1), [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(Palette::named("nope"), None);
+        assert_eq!(Palette::named("Classic"), None);
+    }
+
+    #[test]
+    fn parses_hex_lists() {
+        let palette = Palette::parse("#000000, 33ff66,#112233,#AABBCC").unwrap();
+        assert_eq!(palette.colors, [[0, 0, 0, 0xff], [0x33, 0xff, 0x66, 0xff], [0x11, 0x22, 0x33, 0xff], [0xaa, 0xbb, 0xcc, 0xff]]);
+
+        // Left out colors repeat the first plane color.
+        let palette = Palette::parse("#000000,#33ff66").unwrap();
+        assert_eq!(palette.colors[2], [0x33, 0xff, 0x66, 0xff]);
+        assert_eq!(palette.colors[3], [0x33, 0xff, 0x66, 0xff]);
+        assert_eq!(palette.color(7), palette.colors[3]);
+    }
+
+    #[test]
+    fn rejects_bad_hex_lists() {
+        assert!(Palette::parse("#000000").is_err());
+        assert!(Palette::parse("#000000,#111111,#222222,#333333,#444444").is_err());
+        assert!(Palette::parse("").is_err());
+        assert!(Palette::parse("#000000,#fff").is_err());
+        assert!(Palette::parse("#000000,#1234567").is_err());
+        assert!(Palette::parse("#000000,#gggggg").is_err());
+        assert!(Palette::parse("#000000,").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::display::{CHIP8_HEIGHT, CHIP8_WIDTH};
+use crate::palette::Palette;
+
+const PIXELS: usize = (CHIP8_WIDTH * CHIP8_HEIGHT) as usize;
+
+/// How frames are combined before they are shown. This only changes what the
+/// window presents; `World.px` is never touched.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Persistence {
+    /// Show the raw framebuffer.
+    Off,
+    /// Pixels that turn off fade out, keeping this fraction of their brightness each frame.
+    Blend(f32),
+    /// Show a pixel if it was lit in this frame or the previous one.
+    Or,
+}
+
+pub const DEFAULT_DECAY: f32 = 0.6;
+
+impl Persistence {
+    pub fn parse(mode: &str, decay: f32) -> Result<Persistence, String> {
+        match mode {
+            "off" => Ok(Persistence::Off),
+            "blend" => Ok(Persistence::Blend(decay)),
+            "or" => Ok(Persistence::Or),
+            _ => Err(format!("Unknown persistence mode: {mode} (expected off, blend or or)")),
+        }
+    }
+}
+
+/// Presentation-side pixel history used to smooth out XOR-erase flicker.
+pub struct Phosphor {
+    mode: Persistence,
+    /// Brightness of each pixel between 0.0 and 1.0, for `Blend`.
+    intensity: [f32; PIXELS],
+    /// Last lit value of each pixel, so fading pixels keep their plane color.
+    lit: [u8; PIXELS],
+    /// The framebuffers of the last two emulated frames, for `Or`.
+    current: [u8; PIXELS],
+    previous: [u8; PIXELS],
+}
+
+impl Phosphor {
+    pub fn new(mode: Persistence) -> Self {
+        Self { mode, intensity: [0.0; PIXELS], lit: [0; PIXELS], current: [0; PIXELS], previous: [0; PIXELS] }
+    }
+
+    /// Feed the framebuffer of a finished emulated frame. Call once per frame, not per redraw,
+    /// so the decay speed does not depend on the host refresh rate.
+    pub fn update(&mut self, px: &[u8]) {
+        match self.mode {
+            Persistence::Off => {}
+            Persistence::Blend(decay) => {
+                for (i, &pixel) in px.iter().enumerate() {
+                    if pixel != 0 {
+                        self.intensity[i] = 1.0;
+                        self.lit[i] = pixel;
+                    } else {
+                        self.intensity[i] *= decay;
+                    }
+                }
+            }
+            Persistence::Or => {
+                self.previous = self.current;
+                self.current.copy_from_slice(px);
+            }
+        }
+    }
+
+    /// Draw `px` into an RGBA frame, blended with the pixel history.
+    pub fn draw(&self, px: &[u8], frame: &mut [u8], palette: &Palette) {
+        for (i, &pixel) in px.iter().enumerate() {
+            let rgba = match self.mode {
+                Persistence::Off => palette.color(pixel),
+                Persistence::Blend(_) if pixel == 0 => {
+                    mix(palette.color(0), palette.color(self.lit[i]), self.intensity[i])
+                }
+                Persistence::Blend(_) => palette.color(pixel),
+                Persistence::Or => palette.color(pixel | self.previous[i]),
+            };
+
+            let offset = i * 4;
+            frame[offset..offset + 4].copy_from_slice(&rgba);
+        }
+    }
+}
+
+fn mix(from: [u8; 4], to: [u8; 4], amount: f32) -> [u8; 4] {
+    let mut out = [0xff; 4];
+    for c in 0..3 {
+        out[c] = (from[c] as f32 + (to[c] as f32 - from[c] as f32) * amount).round() as u8;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(pixel: u8) -> Vec<u8> {
+        let mut px = vec![0; PIXELS];
+        px[0] = pixel;
+        px
+    }
+
+    fn first_pixel(phosphor: &Phosphor, px: &[u8]) -> [u8; 4] {
+        let mut out = vec![0; PIXELS * 4];
+        phosphor.draw(px, &mut out, &Palette::default());
+        out[..4].try_into().unwrap()
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!(Persistence::parse("off", 0.5), Ok(Persistence::Off));
+        assert_eq!(Persistence::parse("blend", 0.5), Ok(Persistence::Blend(0.5)));
+        assert_eq!(Persistence::parse("or", 0.5), Ok(Persistence::Or));
+        assert!(Persistence::parse("fade", 0.5).is_err());
+    }
+
+    #[test]
+    fn off_shows_the_raw_framebuffer() {
+        let mut phosphor = Phosphor::new(Persistence::Off);
+        phosphor.update(&frame(1));
+        assert_eq!(first_pixel(&phosphor, &frame(0)), [0, 0, 0, 0xff]);
+        assert_eq!(first_pixel(&phosphor, &frame(1)), [0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn blend_fades_pixels_that_turn_off() {
+        let mut phosphor = Phosphor::new(Persistence::Blend(0.5));
+        phosphor.update(&frame(1));
+        assert_eq!(first_pixel(&phosphor, &frame(1)), [0xff, 0xff, 0xff, 0xff]);
+
+        phosphor.update(&frame(0));
+        assert_eq!(first_pixel(&phosphor, &frame(0)), [0x80, 0x80, 0x80, 0xff]);
+        phosphor.update(&frame(0));
+        assert_eq!(first_pixel(&phosphor, &frame(0)), [0x40, 0x40, 0x40, 0xff]);
+
+        // Lighting the pixel again restores full brightness.
+        phosphor.update(&frame(1));
+        assert_eq!(first_pixel(&phosphor, &frame(1)), [0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn blend_fades_towards_the_last_plane_color() {
+        let mut phosphor = Phosphor::new(Persistence::Blend(1.0));
+        phosphor.update(&frame(2));
+        phosphor.update(&frame(0));
+        assert_eq!(first_pixel(&phosphor, &frame(0)), [0xaa, 0xaa, 0xaa, 0xff]);
+    }
+
+    #[test]
+    fn or_keeps_pixels_lit_for_one_more_frame() {
+        let mut phosphor = Phosphor::new(Persistence::Or);
+        phosphor.update(&frame(1));
+        phosphor.update(&frame(0));
+        assert_eq!(first_pixel(&phosphor, &frame(0)), [0xff, 0xff, 0xff, 0xff]);
+
+        phosphor.update(&frame(0));
+        assert_eq!(first_pixel(&phosphor, &frame(0)), [0, 0, 0, 0xff]);
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+
+/// The CHIP-8 variant a ROM is written for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Platform {
+    /// The original interpreter: 4 KiB of memory, programs loaded at 0x200.
+    Chip8,
+    /// CHIP-8 on the COSMAC VIP, run with the VIP's instruction timing.
+    CosmacVip,
+    /// SUPER-CHIP 1.1 on the HP48, also 4 KiB.
+    SuperChip,
+    /// Octo's XO-CHIP with 64 KiB of memory.
+    XoChip,
+}
+
+pub const PROGRAM_START: usize = 0x200;
+
+impl Platform {
+    pub fn parse(name: &str) -> Result<Platform, String> {
+        match name {
+            "chip8" => Ok(Platform::Chip8),
+            "vip" => Ok(Platform::CosmacVip),
+            "schip" => Ok(Platform::SuperChip),
+            "xochip" => Ok(Platform::XoChip),
+            _ => Err(format!("Unknown platform: {name} (expected chip8, vip, schip or xochip)")),
+        }
+    }
+
+    /// The name `parse` accepts for this platform.
+    pub fn id(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::CosmacVip => "vip",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+        }
+    }
+
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::CosmacVip | Platform::SuperChip => 0x1000,
+            Platform::XoChip => 0x10000,
+        }
+    }
+
+    /// Largest program that fits between `PROGRAM_START` and the end of memory.
+    pub fn max_rom_size(self) -> usize {
+        self.memory_size() - PROGRAM_START
+    }
+
+    /// The platform whose instructions this one runs; the VIP runs plain CHIP-8.
+    pub fn instruction_set(self) -> Platform {
+        match self {
+            Platform::CosmacVip => Platform::Chip8,
+            _ => self,
+        }
+    }
+
+    /// Whether this interpreter can run programs for the platform.
+    pub fn is_supported(self) -> bool {
+        self.memory_size() <= 0x1000
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Platform::Chip8 => "CHIP-8",
+            Platform::CosmacVip => "COSMAC VIP",
+            Platform::SuperChip => "SUPER-CHIP",
+            Platform::XoChip => "XO-CHIP",
+        };
+        f.write_str(name)
+    }
+}
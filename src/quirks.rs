@@ -0,0 +1,153 @@
+use crate::platform::Platform;
+
+/// Behaviour differences between CHIP-8 interpreters that programs rely on. The names
+/// follow the CHIP-8 community program database.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Quirks {
+    /// 8XY6/8XYE shift VX in place instead of loading the shifted VY.
+    pub shift: bool,
+    /// FX55/FX65 advance I by X instead of X + 1.
+    pub memory_increment_by_x: bool,
+    /// FX55/FX65 leave I unchanged.
+    pub memory_leave_i_unchanged: bool,
+    /// Sprites wrap around the screen edges instead of being clipped.
+    pub wrap: bool,
+    /// BNNN jumps to XNN + VX instead of NNN + V0.
+    pub jump: bool,
+    /// DXYN waits for the next 60 Hz frame, so a frame draws at most one sprite.
+    pub vblank: bool,
+    /// 8XY1/8XY2/8XY3 reset VF to 0.
+    pub logic: bool,
+}
+
+pub const QUIRK_NAMES: [&str; 7] = ["shift", "memoryIncrementByX", "memoryLeaveIUnchanged", "wrap", "jump", "vblank", "logic"];
+
+impl Quirks {
+    pub const NONE: Quirks = Quirks {
+        shift: false,
+        memory_increment_by_x: false,
+        memory_leave_i_unchanged: false,
+        wrap: false,
+        jump: false,
+        vblank: false,
+        logic: false,
+    };
+
+    /// The behaviour of this interpreter before quirks were configurable, which matches
+    /// most modern CHIP-8 programs.
+    pub const MODERN: Quirks = Quirks {
+        shift: true,
+        memory_increment_by_x: false,
+        memory_leave_i_unchanged: true,
+        wrap: true,
+        jump: false,
+        vblank: false,
+        logic: false,
+    };
+
+    /// The original COSMAC VIP interpreter.
+    pub const VIP: Quirks = Quirks {
+        shift: false,
+        memory_increment_by_x: false,
+        memory_leave_i_unchanged: false,
+        wrap: false,
+        jump: false,
+        vblank: true,
+        logic: true,
+    };
+
+    /// SUPER-CHIP 1.1.
+    pub const SCHIP: Quirks = Quirks {
+        shift: true,
+        memory_increment_by_x: false,
+        memory_leave_i_unchanged: true,
+        wrap: false,
+        jump: true,
+        vblank: false,
+        logic: false,
+    };
+
+    pub fn for_platform(platform: Platform) -> Quirks {
+        match platform {
+            Platform::Chip8 => Quirks::MODERN,
+            Platform::CosmacVip => Quirks::VIP,
+            Platform::SuperChip => Quirks::SCHIP,
+            Platform::XoChip => Quirks { wrap: true, vblank: false, ..Quirks::VIP },
+        }
+    }
+
+    /// Parse a preset name (`modern`, `vip`, `schip`), `none`, or a comma separated list
+    /// of the quirks to enable, e.g. `shift,jump`.
+    pub fn parse(list: &str) -> Result<Quirks, String> {
+        match list {
+            "modern" => return Ok(Quirks::MODERN),
+            "vip" => return Ok(Quirks::VIP),
+            "schip" => return Ok(Quirks::SCHIP),
+            _ => {}
+        }
+
+        let mut quirks = Quirks::NONE;
+        if list == "none" {
+            return Ok(quirks);
+        }
+        for name in list.split(',').map(str::trim) {
+            if !quirks.set(name, true) {
+                return Err(format!("Unknown quirk: {name} (expected {})", QUIRK_NAMES.join(", ")));
+            }
+        }
+        Ok(quirks)
+    }
+
+    /// Turn a quirk on or off by name; returns false for unknown names.
+    pub fn set(&mut self, name: &str, on: bool) -> bool {
+        let flag = match name {
+            "shift" => &mut self.shift,
+            "memoryIncrementByX" => &mut self.memory_increment_by_x,
+            "memoryLeaveIUnchanged" => &mut self.memory_leave_i_unchanged,
+            "wrap" => &mut self.wrap,
+            "jump" => &mut self.jump,
+            "vblank" => &mut self.vblank,
+            "logic" => &mut self.logic,
+            _ => return false,
+        };
+        *flag = on;
+        true
+    }
+
+    /// The enabled quirks as a list `parse` accepts.
+    pub fn to_list(self) -> String {
+        let flags = [self.shift, self.memory_increment_by_x, self.memory_leave_i_unchanged, self.wrap, self.jump, self.vblank, self.logic];
+        let enabled: Vec<&str> = QUIRK_NAMES.iter().zip(flags).filter(|(_, on)| *on).map(|(name, _)| *name).collect();
+        if enabled.is_empty() { "none".to_string() } else { enabled.join(",") }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::MODERN
+    }
+}
+
+/// What happens when I, or an access through it, runs past the end of the 4 KiB memory.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum IOverflow {
+    /// Addresses wrap around to 0x000, as on hardware.
+    #[default]
+    Wrap,
+    /// Stop the machine with an `EmuError`.
+    Fault,
+    /// Wrap, and make FX1E set VF to 1 when I overflows and to 0 otherwise, as the Amiga
+    /// interpreter did (Spacefight 2091! relies on it).
+    Flag,
+}
+
+impl IOverflow {
+    pub fn parse(name: &str) -> Result<IOverflow, String> {
+        match name {
+            "wrap" => Ok(IOverflow::Wrap),
+            "fault" => Ok(IOverflow::Fault),
+            "flag" => Ok(IOverflow::Flag),
+            _ => Err(format!("Unknown I overflow mode: {name} (expected wrap, fault or flag)")),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::display::{World, CHIP8_HEIGHT, CHIP8_WIDTH};
+use crate::palette::Palette;
+use crate::screenshot::{render_indexed, render_scaled, write_png};
+
+/// Frames per second the recording is timed at (one emulated frame per capture).
+const FPS: u32 = 60;
+
+enum Output {
+    /// Animated GIF. Identical consecutive frames are merged into one longer frame, so the
+    /// pending frame is only written once the screen changes or the recording stops.
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        pending: Option<(Vec<u8>, u32)>,
+    },
+    /// Uncompressed YUV4MPEG2 stream, 4:4:4 so single pixels stay sharp.
+    Y4m(BufWriter<File>),
+    /// Directory of numbered PNG files.
+    Png(PathBuf),
+}
+
+/// Captures `World.px` once per frame into a GIF, a Y4M stream or a directory of PNG frames.
+///
+/// The output kind is picked from the path: `.gif`, `.y4m`, anything else is a directory.
+pub struct Recorder {
+    output: Output,
+    path: PathBuf,
+    scale: u32,
+    palette: Palette,
+    frames: u32,
+}
+
+impl Recorder {
+    pub fn start(path: &Path, scale: u32, palette: &Palette) -> Result<Recorder, String> {
+        let width = CHIP8_WIDTH.saturating_mul(scale);
+        let height = CHIP8_HEIGHT.saturating_mul(scale);
+        let err = |e: std::io::Error| format!("{}: {e}", path.display());
+
+        let output = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gif") => {
+                let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+                    return Err(format!("{}: {width}x{height} is too large for a GIF", path.display()));
+                };
+                let file = BufWriter::new(File::create(path).map_err(err)?);
+                let rgb: Vec<u8> = palette.colors.iter().flat_map(|rgba| rgba[..3].to_vec()).collect();
+                let mut encoder = gif::Encoder::new(file, width, height, &rgb)
+                    .map_err(|e| e.to_string())?;
+                encoder.set_repeat(gif::Repeat::Infinite).map_err(|e| e.to_string())?;
+                Output::Gif { encoder, pending: None }
+            }
+            Some("y4m") => {
+                let mut file = BufWriter::new(File::create(path).map_err(err)?);
+                writeln!(file, "YUV4MPEG2 W{width} H{height} F{FPS}:1 Ip A1:1 C444").map_err(err)?;
+                Output::Y4m(file)
+            }
+            _ => {
+                fs::create_dir_all(path).map_err(err)?;
+                Output::Png(path.to_path_buf())
+            }
+        };
+
+        Ok(Recorder { output, path: path.to_path_buf(), scale, palette: *palette, frames: 0 })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the current screen as the next frame.
+    pub fn capture(&mut self, world: &World) -> Result<(), String> {
+        let width = CHIP8_WIDTH * self.scale;
+        let height = CHIP8_HEIGHT * self.scale;
+
+        match &mut self.output {
+            Output::Gif { encoder, pending } => {
+                let indices = render_indexed(world, self.scale);
+                match pending {
+                    Some((last, _)) if *last == indices => {}
+                    _ => {
+                        if let Some((last, start)) = pending.take() {
+                            write_gif_frame(encoder, last, start, self.frames, width, height)?;
+                        }
+                        *pending = Some((indices, self.frames));
+                    }
+                }
+            }
+            Output::Y4m(file) => {
+                let image = render_scaled(world, self.scale, &self.palette);
+                file.write_all(b"FRAME\n").map_err(|e| e.to_string())?;
+                file.write_all(&rgba_to_yuv444(&image)).map_err(|e| e.to_string())?;
+            }
+            Output::Png(dir) => {
+                let image = render_scaled(world, self.scale, &self.palette);
+                let path = dir.join(format!("frame-{:06}.png", self.frames));
+                write_png(&path, &image, width, height)?;
+            }
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Flush everything to disk and return the number of captured frames.
+    pub fn finish(self) -> Result<u32, String> {
+        let width = CHIP8_WIDTH * self.scale;
+        let height = CHIP8_HEIGHT * self.scale;
+
+        match self.output {
+            Output::Gif { mut encoder, pending } => {
+                if let Some((last, start)) = pending {
+                    write_gif_frame(&mut encoder, last, start, self.frames, width, height)?;
+                }
+                encoder.into_inner().map_err(|e| e.to_string())?.flush().map_err(|e| e.to_string())?;
+            }
+            Output::Y4m(mut file) => file.flush().map_err(|e| e.to_string())?,
+            Output::Png(_) => {}
+        }
+        Ok(self.frames)
+    }
+}
+
+/// Write a GIF frame shown from frame `start` up to frame `end`. GIF delays are in
+/// hundredths of a second, so delays are rounded against the absolute timeline to avoid drift.
+fn write_gif_frame(
+    encoder: &mut gif::Encoder<BufWriter<File>>,
+    indices: Vec<u8>,
+    start: u32,
+    end: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), String> {
+    let centis = |frame: u32| (frame * 100 + FPS / 2) / FPS;
+    let mut frame = gif::Frame::from_indexed_pixels(width as u16, height as u16, indices, None);
+    frame.delay = (centis(end) - centis(start)).max(1) as u16;
+    encoder.write_frame(&frame).map_err(|e| e.to_string())
+}
+
+/// Convert RGBA pixels to planar BT.601 Y, U and V planes.
+fn rgba_to_yuv444(image: &[u8]) -> Vec<u8> {
+    let pixels = image.len() / 4;
+    let mut planes = vec![0; pixels * 3];
+
+    for (i, rgba) in image.chunks(4).enumerate() {
+        let (r, g, b) = (rgba[0] as f32, rgba[1] as f32, rgba[2] as f32);
+        let y = 16.0 + 0.257 * r + 0.504 * g + 0.098 * b;
+        let u = 128.0 - 0.148 * r - 0.291 * g + 0.439 * b;
+        let v = 128.0 + 0.439 * r - 0.368 * g - 0.071 * b;
+        planes[i] = y.round() as u8;
+        planes[pixels + i] = u.round() as u8;
+        planes[2 * pixels + i] = v.round() as u8;
+    }
+    planes
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::platform::Platform;
+
+/// Why a ROM could not be loaded.
+#[derive(Debug)]
+pub enum RomError {
+    Io(String),
+    Empty,
+    TooLarge { size: usize, platform: Platform },
+    Unsupported(Platform),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Io(err) => write!(f, "{err}"),
+            RomError::Empty => write!(f, "the ROM file is empty"),
+            RomError::TooLarge { size, platform } => write!(
+                f,
+                "the ROM is {size} bytes, but {platform} programs can be at most {} bytes",
+                platform.max_rom_size()
+            ),
+            RomError::Unsupported(platform) => write!(f, "{platform} programs are not supported by this interpreter"),
+        }
+    }
+}
+
+/// A validated program image with its hashes and a guess of the platform it targets.
+pub struct Rom {
+    pub data: Vec<u8>,
+    /// Lowercase hex SHA-1, the key used by the CHIP-8 community program database.
+    pub sha1: String,
+    pub crc32: u32,
+    /// The platform suggested by the opcodes found in the program.
+    pub detected: Platform,
+}
+
+impl Rom {
+    /// Check that `data` fits into the memory of `platform` and gather its metadata.
+    pub fn from_bytes(data: Vec<u8>, platform: Platform) -> Result<Rom, RomError> {
+        if !platform.is_supported() {
+            return Err(RomError::Unsupported(platform));
+        }
+        if data.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if data.len() > platform.max_rom_size() {
+            return Err(RomError::TooLarge { size: data.len(), platform });
+        }
+
+        let sha1 = sha1_hex(&data);
+        let crc32 = crc32fast::hash(&data);
+        let detected = detect_platform(&data);
+        Ok(Rom { data, sha1, crc32, detected })
+    }
+}
+
+/// Read a ROM image without validating it.
+pub fn read_rom(path: &Path) -> Result<Vec<u8>, RomError> {
+    fs::read(path).map_err(|e| RomError::Io(format!("{}: {e}", path.display())))
+}
+
+/// Lowercase hex SHA-1 of a ROM image.
+pub fn sha1_hex(data: &[u8]) -> String {
+    sha1_smol::Sha1::from(data).digest().to_string()
+}
+
+/// Guess the platform from the instructions a program uses. Programs interleave code
+/// and data, so this only looks at even offsets and is a hint, not a guarantee.
+pub fn detect_platform(data: &[u8]) -> Platform {
+    let mut platform = Platform::Chip8;
+
+    for pair in data.chunks_exact(2) {
+        let opcode = u16::from_be_bytes([pair[0], pair[1]]);
+        let xo_chip = matches!(opcode & 0xF00F, 0x5002 | 0x5003) || matches!(opcode & 0xF0FF, 0xF001 | 0xF03A);
+        let super_chip = matches!(opcode, 0x00FB..=0x00FF)
+            || opcode & 0xFFF0 == 0x00C0
+            || opcode & 0xF00F == 0xD000
+            || matches!(opcode & 0xF0FF, 0xF030 | 0xF075 | 0xF085);
+
+        if xo_chip {
+            return Platform::XoChip;
+        }
+        if super_chip {
+            platform = Platform::SuperChip;
+        }
+    }
+    platform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn too_large(result: Result<Rom, RomError>) -> Option<(usize, Platform)> {
+        match result {
+            Err(RomError::TooLarge { size, platform }) => Some((size, platform)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn rejects_empty_roms() {
+        assert!(matches!(Rom::from_bytes(Vec::new(), Platform::Chip8), Err(RomError::Empty)));
+    }
+
+    #[test]
+    fn rejects_roms_larger_than_memory() {
+        for platform in [Platform::Chip8, Platform::CosmacVip, Platform::SuperChip] {
+            assert_eq!(platform.max_rom_size(), 0xE00);
+            assert!(Rom::from_bytes(vec![0; 0xE00], platform).is_ok());
+            assert_eq!(too_large(Rom::from_bytes(vec![0; 0xE01], platform)), Some((0xE01, platform)));
+        }
+        assert!(matches!(Rom::from_bytes(vec![0; 2], Platform::XoChip), Err(RomError::Unsupported(Platform::XoChip))));
+    }
+
+    #[test]
+    fn hashes_the_rom() {
+        // The FIPS 180 and CRC-32 check values for "abc".
+        let rom = Rom::from_bytes(b"abc".to_vec(), Platform::Chip8).unwrap();
+        assert_eq!(rom.sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(rom.crc32, 0x352441c2);
+    }
+
+    #[test]
+    fn detects_the_platform_from_opcodes() {
+        assert_eq!(detect_platform(&[0x00, 0xE0, 0xD0, 0x15, 0x12, 0x00]), Platform::Chip8);
+        // Scroll down, 16x16 sprite, big font digit.
+        assert_eq!(detect_platform(&[0x00, 0xC4]), Platform::SuperChip);
+        assert_eq!(detect_platform(&[0xD1, 0x20]), Platform::SuperChip);
+        assert_eq!(detect_platform(&[0xF2, 0x30]), Platform::SuperChip);
+        // XO-CHIP opcodes win over SUPER-CHIP ones: plane select, save a register range.
+        assert_eq!(detect_platform(&[0x00, 0xFF, 0xF0, 0x00]), Platform::SuperChip);
+        assert_eq!(detect_platform(&[0x00, 0xFF, 0xF0, 0x01]), Platform::XoChip);
+        assert_eq!(detect_platform(&[0x51, 0x22]), Platform::XoChip);
+        // Only even offsets are opcodes.
+        assert_eq!(detect_platform(&[0x12, 0x00, 0xFF]), Platform::Chip8);
+        assert_eq!(detect_platform(&[0x60, 0x00, 0xFB, 0x00]), Platform::Chip8);
+    }
+}
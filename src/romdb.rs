@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+
+/// What the database knows about one ROM image.
+#[derive(Debug, PartialEq)]
+pub struct RomEntry {
+    pub title: String,
+    pub platform: Option<Platform>,
+    pub quirks: Option<Quirks>,
+    pub ipf: Option<u32>,
+    /// Pixel colors as `#rrggbb` strings.
+    pub colors: Vec<String>,
+    /// Host keys for CHIP-8 keys 0x0..=0xF. Not part of the community format.
+    pub keymap: Option<String>,
+}
+
+/// Program database keyed by the SHA-1 of the ROM image, in the format of the CHIP-8
+/// community program database (`programs.json`).
+pub struct RomDb {
+    programs: Value,
+}
+
+impl RomDb {
+    pub fn load(path: &Path) -> Result<RomDb, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        RomDb::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<RomDb, String> {
+        let programs: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        if !programs.is_array() {
+            return Err("expected an array of programs".to_string());
+        }
+        Ok(RomDb { programs })
+    }
+
+    pub fn lookup(&self, sha1: &str) -> Option<RomEntry> {
+        self.programs.as_array()?.iter().find_map(|program| {
+            let rom = program["roms"].get(sha1)?;
+            Some(entry(program, rom))
+        })
+    }
+}
+
+fn entry(program: &Value, rom: &Value) -> RomEntry {
+    let title = program["title"].as_str().unwrap_or("Untitled").to_string();
+
+    // ROMs list every platform they run on, best first; use the first one we emulate.
+    let found = rom["platforms"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .find_map(|id| platform_for(id).filter(|(platform, _)| platform.is_supported()).map(|found| (id, found)));
+
+    let (platform, quirks) = match found {
+        Some((id, (platform, mut quirks))) => {
+            for (name, on) in rom["quirkyPlatforms"][id].as_object().into_iter().flatten() {
+                quirks.set(name, on.as_bool().unwrap_or(false));
+            }
+            (Some(platform), Some(quirks))
+        }
+        None => (None, None),
+    };
+
+    let colors = rom["colors"]["pixels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect();
+
+    RomEntry {
+        title,
+        platform,
+        quirks,
+        ipf: rom["tickrate"].as_u64().map(|ipf| ipf as u32),
+        colors,
+        keymap: rom["keymap"].as_str().map(str::to_string),
+    }
+}
+
+/// Platform and quirks for a platform id of the community database.
+fn platform_for(id: &str) -> Option<(Platform, Quirks)> {
+    match id {
+        "originalChip8" | "hybridVIP" => Some((Platform::Chip8, Quirks::VIP)),
+        "modernChip8" => Some((Platform::Chip8, Quirks::MODERN)),
+        "chip48" => Some((Platform::Chip8, Quirks { memory_increment_by_x: true, memory_leave_i_unchanged: false, ..Quirks::SCHIP })),
+        "superchip1" | "superchip" => Some((Platform::SuperChip, Quirks::SCHIP)),
+        "xochip" => Some((Platform::XoChip, Quirks::for_platform(Platform::XoChip))),
+        _ => None,
+    }
+}
+
+impl RomEntry {
+    /// The entry as command line options, so it can be layered with the config file.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(platform) = self.platform {
+            args.extend(["--platform".to_string(), platform.id().to_string()]);
+        }
+        if let Some(quirks) = self.quirks {
+            args.extend(["--quirks".to_string(), quirks.to_list()]);
+        }
+        if let Some(ipf) = self.ipf {
+            args.extend(["--ipf".to_string(), ipf.to_string()]);
+        }
+        if (2..=4).contains(&self.colors.len()) {
+            args.extend(["--colors".to_string(), self.colors.join(",")]);
+        }
+        if let Some(keymap) = &self.keymap {
+            args.extend(["--keymap".to_string(), keymap.clone()]);
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DB: &str = r##"[
+        {
+            "title": "Pong",
+            "roms": {
+                "aaaa": { "platforms": ["originalChip8"], "tickrate": 15, "colors": { "pixels": ["#000000", "#33ff66"] } },
+                "bbbb": { "platforms": ["xochip", "superchip", "chip48"], "quirkyPlatforms": { "superchip": { "shift": false } } }
+            }
+        },
+        { "title": "Octojam Demo", "roms": { "cccc": { "platforms": ["xochip"], "keymap": "x123qweasdzc4rfv" } } }
+    ]"##;
+
+    #[test]
+    fn looks_roms_up_by_hash() {
+        let db = RomDb::parse(DB).unwrap();
+        assert_eq!(db.lookup("aaaa").unwrap().title, "Pong");
+        assert_eq!(db.lookup("cccc").unwrap().title, "Octojam Demo");
+        assert_eq!(db.lookup("dddd"), None);
+        assert!(RomDb::parse("{}").is_err());
+    }
+
+    #[test]
+    fn skips_platforms_we_do_not_run() {
+        let db = RomDb::parse(DB).unwrap();
+        let entry = db.lookup("bbbb").unwrap();
+        assert_eq!(entry.platform, Some(Platform::SuperChip));
+        assert_eq!(entry.quirks, Some(Quirks { shift: false, ..Quirks::SCHIP }));
+
+        let entry = db.lookup("cccc").unwrap();
+        assert_eq!((entry.platform, entry.quirks), (None, None));
+        assert_eq!(entry.keymap.as_deref(), Some("x123qweasdzc4rfv"));
+    }
+
+    #[test]
+    fn entry_becomes_command_line_options() {
+        let entry = RomDb::parse(DB).unwrap().lookup("aaaa").unwrap();
+        let expected = ["--platform", "chip8", "--quirks", &Quirks::VIP.to_list(), "--ipf", "15", "--colors", "#000000,#33ff66"];
+        assert_eq!(entry.to_args(), expected);
+
+        let entry = RomDb::parse(DB).unwrap().lookup("cccc").unwrap();
+        assert_eq!(entry.to_args(), ["--keymap", "x123qweasdzc4rfv"]);
+    }
+}
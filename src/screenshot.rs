@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::display::{World, CHIP8_HEIGHT, CHIP8_WIDTH};
+use crate::palette::Palette;
+
+/// Expand `World.px` so every CHIP-8 pixel becomes a `scale` x `scale` block of its pixel value.
+pub fn render_indexed(world: &World, scale: u32) -> Vec<u8> {
+    let width = (CHIP8_WIDTH * scale) as usize;
+    let height = (CHIP8_HEIGHT * scale) as usize;
+    let mut image = vec![0; width * height];
+
+    for y in 0..height {
+        for x in 0..width {
+            let src = (y / scale as usize) * CHIP8_WIDTH as usize + x / scale as usize;
+            image[y * width + x] = world.px[src] & 3;
+        }
+    }
+    image
+}
+
+/// Like `render_indexed`, but with every pixel looked up in the palette as RGBA.
+pub fn render_scaled(world: &World, scale: u32, palette: &Palette) -> Vec<u8> {
+    render_indexed(world, scale).iter().flat_map(|&px| palette.color(px)).collect()
+}
+
+/// Write the current screen to a PNG file at the given scale and palette.
+pub fn save_png(world: &World, path: &Path, scale: u32, palette: &Palette) -> Result<(), String> {
+    write_png(path, &render_scaled(world, scale, palette), CHIP8_WIDTH * scale, CHIP8_HEIGHT * scale)
+}
+
+/// Encode an RGBA image of the given size to a PNG file.
+pub fn write_png(path: &Path, image: &[u8], width: u32, height: u32) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(image).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())
+}
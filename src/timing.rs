@@ -0,0 +1,96 @@
+use crate::platform::Platform;
+
+/// Machine cycles (8 clocks of the 1.76 MHz CDP1802) in one 60 Hz frame of the VIP.
+pub const VIP_CYCLES_PER_FRAME: i32 = 3668;
+/// Cycles of each frame taken by the display's DMA and the interrupt routine, which
+/// leave the rest to the interpreter.
+pub const VIP_DISPLAY_CYCLES: i32 = 1024 + 46;
+/// Cycles the interpreter loop spends fetching and decoding every instruction.
+const VIP_FETCH_CYCLES: i32 = 40;
+
+/// How many instructions a frame runs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Timing {
+    /// A fixed number of instructions per frame, `--ipf`.
+    #[default]
+    Ipf,
+    /// As many as the COSMAC VIP interpreter would execute, each instruction taking
+    /// its time on the VIP.
+    Vip,
+}
+
+impl Timing {
+    pub fn for_platform(platform: Platform) -> Timing {
+        match platform {
+            Platform::CosmacVip => Timing::Vip,
+            Platform::Chip8 | Platform::SuperChip | Platform::XoChip => Timing::Ipf,
+        }
+    }
+}
+
+/// Machine cycles the VIP interpreter takes for `opcode`, with `vx` the value VX had
+/// before it ran. `skipped` tells whether a skip instruction skipped. The costs are
+/// approximations of the interpreter's routines; what matters to programs is their
+/// relative size, chiefly the slow screen clear, sprite draws and memory transfers.
+pub fn vip_cycles(opcode: u16, vx: u8, skipped: bool) -> i32 {
+    let x = (opcode >> 8 & 0xF) as usize;
+    let skip = if skipped { 4 } else { 0 };
+    let execute = match opcode >> 12 {
+        0x0 if opcode == 0x00E0 => 680,
+        0x0 if opcode == 0x00EE => 10,
+        0x1 => 12,
+        0x2 => 26,
+        0x3 | 0x4 => 10 + skip,
+        0x5 | 0x9 => 14 + skip,
+        0x6 => 6,
+        0x7 => 10,
+        0x8 => 44,
+        0xA => 12,
+        0xB => 22,
+        0xC => 36,
+        // Every row is shifted into place one bit at a time.
+        0xD => 26 + (opcode & 0xF) as i32 * (30 + 4 * (vx % 8) as i32),
+        0xE => 14 + skip,
+        0xF => match opcode & 0xFF {
+            0x07 | 0x15 | 0x18 => 10,
+            0x0A => 20,
+            0x1E | 0x29 | 0x30 => 16,
+            0x33 => 132,
+            0x55 | 0x65 => 14 + 14 * (x as i32 + 1),
+            _ => 0,
+        },
+        _ => 0,
+    };
+    VIP_FETCH_CYCLES + execute
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vip_cycles_include_the_fetch() {
+        assert_eq!(vip_cycles(0x00E0, 0, false), 720);
+        assert_eq!(vip_cycles(0x6123, 0, false), 46);
+        assert_eq!(vip_cycles(0x0123, 0, false), VIP_FETCH_CYCLES);
+    }
+
+    #[test]
+    fn vip_skips_cost_more_when_taken() {
+        assert_eq!(vip_cycles(0x3000, 0, true) - vip_cycles(0x3000, 0, false), 4);
+        assert_eq!(vip_cycles(0xE09E, 0, true), 58);
+    }
+
+    #[test]
+    fn vip_sprite_rows_cost_more_off_byte_boundaries() {
+        assert_eq!(vip_cycles(0xD005, 0, false), 40 + 26 + 5 * 30);
+        assert_eq!(vip_cycles(0xD005, 3, false), 40 + 26 + 5 * (30 + 12));
+        assert_eq!(vip_cycles(0xD005, 8, false), vip_cycles(0xD005, 0, false));
+    }
+
+    #[test]
+    fn vip_memory_transfers_cost_per_register() {
+        assert_eq!(vip_cycles(0xF255, 0, false), 40 + 14 + 3 * 14);
+        assert_eq!(vip_cycles(0xF065, 0, false), 40 + 14 + 14);
+    }
+}
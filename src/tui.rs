@@ -0,0 +1,199 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+
+use crate::audio::AudioBackend;
+use crate::config::Config;
+use crate::display::{CHIP8_HEIGHT, CHIP8_WIDTH};
+use crate::emu8::{reload, Machine};
+use crate::keyslog::chip8_key;
+use crate::palette::Palette;
+use crate::recorder::Recorder;
+use crate::turbo::Turbo;
+use crate::watch::RomWatcher;
+
+/// Most terminals only report key presses, so a key counts as held for this many
+/// frames after its last press or auto-repeat event.
+const HOLD_FRAMES: u32 = 8;
+
+/// Puts the terminal into raw mode on the alternate screen and restores it on drop,
+/// also when the emulator panics.
+struct TerminalGuard {
+    enhanced_keys: bool,
+}
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+
+        // Terminals implementing the kitty keyboard protocol can report key releases.
+        let enhanced_keys = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if enhanced_keys {
+            execute!(io::stdout(), PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+        Ok(Self { enhanced_keys })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.enhanced_keys {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Run the machine in the terminal, drawing two CHIP-8 rows per text row with `▀`
+/// half blocks: the foreground color is the top pixel, the background the bottom one.
+pub fn run_tui(
+    machine: &mut Machine,
+    audio: &mut dyn AudioBackend,
+    config: &Config,
+    mut recorder: Option<Recorder>,
+    mut watcher: Option<RomWatcher>,
+) -> Result<(), String> {
+    let guard = TerminalGuard::enter().map_err(|e| e.to_string())?;
+    let result = tui_loop(machine, audio, config, &guard, &mut recorder, &mut watcher);
+    drop(guard);
+
+    if let Some(recorder) = recorder {
+        let path = recorder.path().display().to_string();
+        let frames = recorder.finish()?;
+        println!("Recorded {frames} frames to {path}");
+    }
+    result
+}
+
+fn tui_loop(
+    machine: &mut Machine,
+    audio: &mut dyn AudioBackend,
+    config: &Config,
+    guard: &TerminalGuard,
+    recorder: &mut Option<Recorder>,
+    watcher: &mut Option<RomWatcher>,
+) -> Result<(), String> {
+    let mut stdout = io::stdout();
+    // A reloaded ROM brings its own settings.
+    let mut config = config.clone();
+
+    let frame_duration = Duration::from_secs_f64(1.0 / 60.0);
+    let mut next_frame = Instant::now();
+    let mut held = [0u32; 16];
+    let mut shown: Option<Vec<u8>> = None;
+    let mut turbo = config.turbo;
+
+    let io_err = |e: io::Error| e.to_string();
+
+    queue!(
+        stdout,
+        cursor::MoveTo(0, (CHIP8_HEIGHT / 2) as u16),
+        ResetColor,
+        Print("Esc or Ctrl-C to quit, Tab for turbo")
+    )
+    .map_err(io_err)?;
+
+    loop {
+        while event::poll(next_frame.saturating_duration_since(Instant::now())).map_err(io_err)? {
+            let Event::Key(key) = event::read().map_err(io_err)? else { continue };
+            let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if key.code == KeyCode::Esc || ctrl_c {
+                return Ok(());
+            }
+            if key.code == KeyCode::Tab && key.kind == KeyEventKind::Press {
+                turbo = match turbo {
+                    Some(_) => None,
+                    None => Some(config.turbo.unwrap_or(Turbo::Max)),
+                };
+                continue;
+            }
+            let KeyCode::Char(c) = key.code else { continue };
+            let Some(chip8) = chip8_key(&config.keymap, c) else { continue };
+            held[chip8 as usize] = match key.kind {
+                KeyEventKind::Release => 0,
+                _ if guard.enhanced_keys => u32::MAX,
+                _ => HOLD_FRAMES,
+            };
+        }
+        next_frame += frame_duration;
+
+        if let Some((reloaded, rom)) = watcher.as_mut().and_then(|watcher| watcher.poll(&config)) {
+            reload(machine, &rom, &reloaded);
+            config = reloaded;
+            // Reloading prints to the terminal; draw the whole screen again.
+            shown = None;
+        }
+
+        for (key, frames) in held.iter_mut().enumerate() {
+            machine.keyboard[key] = (*frames > 0) as u8;
+            if *frames != u32::MAX {
+                *frames = frames.saturating_sub(1);
+            }
+        }
+
+        match turbo {
+            Some(turbo) => {
+                // The speakers stay quiet while a WAV export still gets every frame.
+                audio.set_muted(true);
+                let mut result = Ok(());
+                let run = turbo.run_tick(machine, |machine| {
+                    if result.is_ok() {
+                        result = audio.push(&machine.samples);
+                    }
+                    if let (Ok(()), Some(recorder)) = (&result, recorder.as_mut()) {
+                        result = recorder.capture(&machine.world);
+                    }
+                });
+                audio.set_muted(false);
+                run.map_err(|err| err.to_string())?;
+                result?;
+            }
+            None => {
+                machine.run_frame().map_err(|err| err.to_string())?;
+                audio.push(&machine.samples)?;
+
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.capture(&machine.world)?;
+                }
+            }
+        }
+
+        if shown.as_deref() != Some(&machine.world.px[..]) {
+            draw(&mut stdout, &machine.world.px, &config.palette).map_err(io_err)?;
+            shown = Some(machine.world.px.to_vec());
+        }
+        stdout.flush().map_err(io_err)?;
+    }
+}
+
+fn draw(out: &mut impl Write, px: &[u8], palette: &Palette) -> io::Result<()> {
+    let width = CHIP8_WIDTH as usize;
+    let mut colors = None;
+
+    for row in 0..(CHIP8_HEIGHT / 2) as usize {
+        queue!(out, cursor::MoveTo(0, row as u16))?;
+        for x in 0..width {
+            let top = rgb(palette.color(px[row * 2 * width + x]));
+            let bottom = rgb(palette.color(px[(row * 2 + 1) * width + x]));
+            if colors != Some((top, bottom)) {
+                queue!(out, SetForegroundColor(top), SetBackgroundColor(bottom))?;
+                colors = Some((top, bottom));
+            }
+            queue!(out, Print('▀'))?;
+        }
+    }
+    queue!(out, ResetColor)
+}
+
+fn rgb(rgba: [u8; 4]) -> Color {
+    Color::Rgb { r: rgba[0], g: rgba[1], b: rgba[2] }
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::emu8::Machine;
+use crate::error::EmuError;
+
+/// Longest a `Turbo::Max` tick may emulate, leaving the rest of the 60 Hz tick for
+/// input and rendering.
+const MAX_TICK: Duration = Duration::from_millis(12);
+
+/// Fast-forward that is not tied to the display rate. Audio is muted while it runs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Turbo {
+    /// This many frames per displayed frame.
+    Times(u32),
+    /// As many frames as the host can emulate.
+    Max,
+}
+
+impl Turbo {
+    pub fn parse(value: &str) -> Result<Turbo, String> {
+        match value {
+            "max" => Ok(Turbo::Max),
+            _ => match value.trim_end_matches('x').parse() {
+                Ok(times) if times >= 2 => Ok(Turbo::Times(times)),
+                _ => Err(format!("Invalid turbo speed: {value} (expected 2x, 4x, 8x, ... or max)")),
+            },
+        }
+    }
+
+    /// Emulate the frames of one displayed frame, calling `frame_done` after each one.
+    /// Returns the number of frames run, or the fault that stopped the machine.
+    pub fn run_tick(self, machine: &mut Machine, mut frame_done: impl FnMut(&Machine)) -> Result<u32, EmuError> {
+        let start = Instant::now();
+        let mut frames = 0;
+        loop {
+            machine.run_frame()?;
+            frame_done(machine);
+            frames += 1;
+            let done = match self {
+                Turbo::Times(times) => frames >= times,
+                Turbo::Max => start.elapsed() >= MAX_TICK,
+            };
+            if done {
+                return Ok(frames);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Turbo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Turbo::Times(times) => write!(f, "{times}x"),
+            Turbo::Max => f.write_str("max"),
+        }
+    }
+}
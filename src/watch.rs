@@ -0,0 +1,161 @@
+use std::fs;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::Config;
+use crate::library::{prepare, RomFile};
+use crate::rom::Rom;
+
+/// How often the ROM file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Watches the file a ROM was loaded from so it can be reloaded after an external
+/// assembler rewrites it.
+pub struct RomWatcher {
+    file: RomFile,
+    /// The command line, parsed again with the settings found for the new ROM.
+    args: Vec<String>,
+    modified: Option<SystemTime>,
+    /// A change that has been seen but may still be in progress.
+    pending: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl RomWatcher {
+    pub fn new(file: RomFile, args: &[String]) -> RomWatcher {
+        let modified = modified_time(&file);
+        RomWatcher { file, args: args.to_vec(), modified, pending: None, last_check: Instant::now() }
+    }
+
+    /// Call once per frame with the running ROM's settings. Returns the new ROM and its
+    /// settings once the file has changed and then stayed the same for one poll
+    /// interval, so half-written files are not loaded. The ROM is looked up in the
+    /// program database like on startup; one that fails validation is reported and
+    /// skipped.
+    pub fn poll(&mut self, config: &Config) -> Option<(Config, Rom)> {
+        if self.last_check.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        // A missing file is usually being rewritten; wait for it to come back.
+        let modified = Some(modified_time(&self.file)?);
+        if modified == self.modified {
+            self.pending = None;
+            return None;
+        }
+        if self.pending != modified {
+            self.pending = modified;
+            return None;
+        }
+
+        self.modified = modified;
+        self.pending = None;
+        match prepare(&self.args, config, &self.file) {
+            Ok(loaded) => Some(loaded),
+            Err(err) => {
+                eprintln!("Warning: {err}; the old ROM keeps running");
+                None
+            }
+        }
+    }
+}
+
+fn modified_time(file: &RomFile) -> Option<SystemTime> {
+    fs::metadata(file.path()).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::config::parse_args;
+    use crate::library::RomLocation;
+    use crate::platform::Platform;
+    use crate::rom::sha1_hex;
+
+    /// An empty directory for one test's files.
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chip8emu-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write the ROM as an assembler would, with a modification time `secs` after the epoch.
+    fn write_rom(path: &Path, data: &[u8], secs: u64) {
+        fs::write(path, data).unwrap();
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+    }
+
+    /// A watcher on `rom.ch8` in `dir` for a command line with an empty config file and
+    /// `options`, with the settings it starts with.
+    fn watch(dir: &Path, options: &[&str]) -> (RomWatcher, Config) {
+        let path = dir.join("rom.ch8");
+        fs::write(dir.join("empty.conf"), "").unwrap();
+        let mut args = vec!["chip8emu".to_string(), path.display().to_string()];
+        args.extend(["--config".to_string(), dir.join("empty.conf").display().to_string()]);
+        args.extend(options.iter().map(|option| option.to_string()));
+        let file = RomFile { name: "rom.ch8".to_string(), location: RomLocation::File(path) };
+        (RomWatcher::new(file, &args), parse_args(&args).unwrap())
+    }
+
+    /// Poll as if the poll interval had passed.
+    fn poll(watcher: &mut RomWatcher, config: &Config) -> Option<Vec<u8>> {
+        watcher.last_check -= POLL_INTERVAL;
+        watcher.poll(config).map(|(_, rom)| rom.data)
+    }
+
+    #[test]
+    fn reloads_once_the_file_stops_changing() {
+        let dir = scratch_dir("debounce");
+        let path = dir.join("rom.ch8");
+        write_rom(&path, &[0x12, 0x00], 1000);
+        let (mut watcher, config) = watch(&dir, &[]);
+        assert_eq!(poll(&mut watcher, &config), None);
+
+        write_rom(&path, &[0x13, 0x00], 2000);
+        // Not checked again before the poll interval is over.
+        assert!(watcher.poll(&config).is_none());
+        assert_eq!(poll(&mut watcher, &config), None);
+        // Still being written.
+        write_rom(&path, &[0x13, 0x00, 0x00, 0xE0], 3000);
+        assert_eq!(poll(&mut watcher, &config), None);
+        assert_eq!(poll(&mut watcher, &config), Some(vec![0x13, 0x00, 0x00, 0xE0]));
+        assert_eq!(poll(&mut watcher, &config), None);
+
+        // A missing file is waited for, an invalid one skipped.
+        fs::remove_file(&path).unwrap();
+        assert_eq!(poll(&mut watcher, &config), None);
+        write_rom(&path, &[], 4000);
+        assert_eq!(poll(&mut watcher, &config), None);
+        assert_eq!(poll(&mut watcher, &config), None);
+        assert_eq!(poll(&mut watcher, &config), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reloaded_rom_gets_its_database_settings() {
+        let dir = scratch_dir("reload");
+        let path = dir.join("rom.ch8");
+        let new_rom = [0x00, 0xE0, 0x12, 0x02];
+        let db = format!(r#"[{{ "title": "New", "roms": {{ "{}": {{ "platforms": ["superchip"], "tickrate": 30 }} }} }}]"#, sha1_hex(&new_rom));
+        fs::write(dir.join("programs.json"), db).unwrap();
+        write_rom(&path, &[0x12, 0x00], 1000);
+        let db_path = dir.join("programs.json").display().to_string();
+        let (mut watcher, config) = watch(&dir, &["--rom-db", &db_path, "--keep-hooks"]);
+        assert_eq!((config.platform, config.ipf), (Platform::Chip8, 1));
+
+        write_rom(&path, &new_rom, 2000);
+        watcher.last_check -= POLL_INTERVAL;
+        assert!(watcher.poll(&config).is_none());
+        watcher.last_check -= POLL_INTERVAL;
+        let (reloaded, rom) = watcher.poll(&config).unwrap();
+        assert_eq!(rom.data, new_rom);
+        assert_eq!((reloaded.platform, reloaded.ipf), (Platform::SuperChip, 30));
+        assert!(reloaded.keep_hooks);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}